@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame};
+use num_complex::Complex;
+use crate::fractal::{self, format_complex, parse_complex, FractalParams};
+use crate::operation::concat_arguments;
+use crate::{Argument, Arguments, Generator, MirageError, Result};
+
+/// Most frames a zoom may have.
+pub const MAX_FRAMES: u32 = 1000;
+
+/// A zoom from the viewport of `start` to `end_center` / `end_zoom`,
+/// rendered as an animation.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ZoomParams {
+    /// First frame; everything but the viewport is shared by all frames.
+    pub start: FractalParams,
+    pub end_center: Complex<f64>,
+    pub end_zoom: f64,
+    pub frames: u32,
+    /// Display time of every frame, in milliseconds.
+    pub delay: u32,
+}
+
+impl Default for ZoomParams {
+    fn default() -> Self {
+        ZoomParams {
+            start: FractalParams { width: 320, height: 240, ..FractalParams::default() },
+            end_center: Complex::new(0.0, 0.0),
+            end_zoom: 100.0,
+            frames: 30,
+            delay: 100,
+        }
+    }
+}
+
+impl Generator for ZoomParams {
+    fn size(&self) -> (u32, u32) {
+        (self.start.width, self.start.height)
+    }
+
+    fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// A single image can only hold the first frame; `app::run` writes the
+    /// whole animation when a chain starts with `zoom`.
+    fn generate(&self) -> Result<DynamicImage> {
+        self.start.generate()
+    }
+}
+
+impl ZoomParams {
+    /// The fractal schema (with a smaller default size) plus the end viewport
+    /// and frame timing.
+    pub const ARGUMENTS: [Argument; 18] = concat_arguments(
+        &[Argument::optional("width", "320"), Argument::optional("height", "240")],
+        &concat_arguments::<16>(
+            FractalParams::ARGUMENTS.split_at(2).1,
+            &[
+                Argument::optional("to", "0+0i").with_hint("end center"),
+                Argument::optional("to-zoom", "100").with_hint("end zoom"),
+                Argument::optional("frames", "30").with_hint("2-1000"),
+                Argument::optional("delay", "100").with_hint("ms per frame"),
+            ],
+        ),
+    );
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let params = ZoomParams {
+            start: FractalParams::from_arguments(args)?,
+            end_center: args.number_with("to", parse_complex)?,
+            end_zoom: args.number("to-zoom")?,
+            frames: args.number("frames")?,
+            delay: args.number("delay")?,
+        };
+        if !(params.end_zoom > 0.0 && params.end_zoom.is_finite()) {
+            return Err(args.invalid("to-zoom", "must be a positive number"));
+        }
+        if !(2..=MAX_FRAMES).contains(&params.frames) {
+            return Err(args.invalid("frames", &format!("must be between 2 and {}", MAX_FRAMES)));
+        }
+        Ok(params)
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        let mut values = self.start.values();
+        values.extend([
+            format_complex(self.end_center),
+            self.end_zoom.to_string(),
+            self.frames.to_string(),
+            self.delay.to_string(),
+        ]);
+        values
+    }
+
+    /// Parameters of every frame.
+    ///
+    /// The zoom grows exponentially, so every frame magnifies by the same
+    /// factor. The center moves in step with the visible width rather than
+    /// linearly, which keeps the end center on a straight path across the
+    /// screen instead of overshooting it.
+    pub fn frame_params(&self) -> Vec<FractalParams> {
+        let start_zoom = self.start.zoom;
+        (0..self.frames)
+            .map(|frame| {
+                let t = frame as f64 / (self.frames - 1) as f64;
+                let zoom = start_zoom * (self.end_zoom / start_zoom).powf(t);
+                let progress = if self.end_zoom == start_zoom {
+                    t
+                } else {
+                    (1.0 / start_zoom - 1.0 / zoom) / (1.0 / start_zoom - 1.0 / self.end_zoom)
+                };
+                let center = self.start.center + (self.end_center - self.start.center) * progress;
+                // A center that moves is only known to `f64` precision.
+                let center_digits = if center == self.start.center { self.start.center_digits.clone() } else { None };
+                FractalParams { center, zoom, center_digits, ..self.start.clone() }
+            })
+            .collect()
+    }
+}
+
+/// Renders the frames of the zoom one at a time, as they are asked for.
+pub fn render_frames(params: &ZoomParams) -> impl Iterator<Item = DynamicImage> {
+    params
+        .frame_params()
+        .into_iter()
+        .map(|frame| DynamicImage::ImageRgb8(fractal::render(&frame)))
+}
+
+/// Checks that `path` names a GIF file, the only format animations are
+/// written in; worth doing before spending time on rendering frames.
+pub fn check_output(path: &str) -> Result<()> {
+    let is_gif = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+    if !is_gif {
+        return Err(MirageError::InvalidArgument {
+            command: "zoom".to_string(),
+            value: path.to_string(),
+            reason: "animations are written as GIF, OUTFILE must end in .gif".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Writes frames as an endlessly looping animated GIF.
+///
+/// Every frame is encoded as soon as `frames` yields it, so only one is held
+/// in memory at a time; the first error stops the animation.
+pub fn save_gif(frames: impl IntoIterator<Item = Result<DynamicImage>>, delay: u32, path: &str) -> Result<()> {
+    check_output(path)?;
+    let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+    encoder.set_repeat(Repeat::Infinite).map_err(MirageError::from_encode)?;
+    let delay = Delay::from_numer_denom_ms(delay, 1);
+    for frame in frames {
+        encoder
+            .encode_frame(Frame::from_parts(frame?.into_rgba8(), 0, 0, delay))
+            .map_err(MirageError::from_encode)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+use std::process;
+use std::thread;
+use clap::{CommandFactory, FromArgMatches};
+use image::DynamicImage;
+use crate::trim::{self, TrimParams};
+use crate::{animation, batch, open_image, pipeline, save_image, script, ChainCommands, Cli, MirageError, Registry, Result};
+
+/// Entry point of the `mirage` binary, parameterised by the operations it knows.
+///
+/// Downstream crates can register their own operations and call this from
+/// their `main` instead of forking mirage.
+pub fn main_with(registry: Registry) {
+    let mut command = Cli::command().after_help(registry.help());
+    let matches = command
+        .try_get_matches_from_mut(Cli::options_first(std::env::args_os()))
+        .unwrap_or_else(|err| err.exit());
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Err(err) = run(&cli, &registry) {
+        if err.is_usage_error() {
+            command.print_help().expect("Should print help list");
+        }
+        eprintln!("error: {}", err);
+        process::exit(err.exit_code());
+    }
+}
+
+/// Parses the chain (script first, then command line), runs it over `--infile` in memory and writes OUTFILE once.
+pub fn run(cli: &Cli, registry: &Registry) -> Result<()> {
+    let mut chain_commands = match &cli.script {
+        Some(script) => script::load_script(script, registry)?,
+        None => Vec::new(),
+    };
+    chain_commands.extend(registry.parse(&cli.command_vector)?);
+    pipeline::validate(cli.infile.is_some() || !cli.batch.is_empty(), &chain_commands)?;
+    let animated = matches!(chain_commands.first(), Some(ChainCommands::Zoom { .. }));
+    if animated {
+        animation::check_output(&cli.outfile)?;
+    }
+    if let Some(path) = &cli.save_script {
+        script::save_script(&chain_commands, path)?;
+        println!("Chain was saved to {}", path);
+    }
+    if !cli.batch.is_empty() {
+        return run_batch(cli, &chain_commands);
+    }
+    if animated {
+        return run_animation(cli, &chain_commands);
+    }
+    // Decode the input once and thread it through the whole chain in memory.
+    let image = match &cli.infile {
+        Some(infile) => {
+            println!("Reading infile {}", infile);
+            Some(open_image(infile)?)
+        }
+        None => None,
+    };
+    let result = pipeline::execute_with(image, &chain_commands, |command, image| {
+        if let (ChainCommands::Trim { params }, Some(img)) = (command, image) {
+            report_trim(img, params);
+        }
+    })?;
+    match result {
+        Some(result) => {
+            save_image(&result, &cli.outfile)?;
+            println!("Result was generated at {:?}", Path::new(&cli.outfile).canonicalize()?);
+        }
+        None => {
+            println!("No file was generated. Provide infile or start the chain with a generator such as fractal or pattern");
+        }
+    }
+    Ok(())
+}
+
+/// Tells what `trim` keeps as the crop command that cuts it out, so the same
+/// cut can be made in other chains without trimming again.
+fn report_trim(img: &DynamicImage, params: &TrimParams) {
+    match trim::rectangle(img, params) {
+        Some(rectangle) => {
+            let command = ChainCommands::Crop { params: rectangle };
+            println!("Trimmed to {}", script::to_script(std::slice::from_ref(&command)).trim_end());
+        }
+        None => println!("Nothing was trimmed, the whole image is border"),
+    }
+}
+
+/// Renders the `zoom` that starts `chain_commands` and runs the rest of the
+/// chain over every frame.
+fn run_animation(cli: &Cli, chain_commands: &[ChainCommands]) -> Result<()> {
+    let ChainCommands::Zoom { params } = &chain_commands[0] else {
+        unreachable!("run_animation is called with the zoom command first");
+    };
+    println!("Rendering {} frames", params.frames);
+    let frames = animation::render_frames(params)
+        .filter_map(|frame| pipeline::execute(Some(frame), &chain_commands[1..]).transpose());
+    animation::save_gif(frames, params.delay, &cli.outfile)?;
+    println!("Animation was generated at {:?}", Path::new(&cli.outfile).canonicalize()?);
+    Ok(())
+}
+
+fn run_batch(cli: &Cli, chain_commands: &[ChainCommands]) -> Result<()> {
+    let inputs = batch::collect_inputs(&cli.batch)?;
+    let jobs = cli.jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, usize::from));
+    let report = batch::run_batch(&inputs, Path::new(&cli.outfile), &cli.template, chain_commands, jobs)?;
+    println!("{}", report.summary());
+    if report.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(MirageError::BatchFailed { failed: report.failed.len(), total: report.total() })
+    }
+}
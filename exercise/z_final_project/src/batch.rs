@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use crossbeam::channel;
+use image::ImageFormat;
+use crate::{open_image, pipeline, save_image, ChainCommands, MirageError, Result};
+
+/// Output name used when no template is given: the input's name with `_out`
+/// added, so a batch written into its own input directory keeps the sources.
+pub const DEFAULT_TEMPLATE: &str = "{stem}_out.{ext}";
+
+/// Outcome of running one chain over many files.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// Input and output path of every file that was written.
+    pub succeeded: Vec<(PathBuf, PathBuf)>,
+    /// Input path of every file that failed, with the reason.
+    pub failed: Vec<(PathBuf, MirageError)>,
+}
+
+impl BatchReport {
+    pub fn total(&self) -> usize {
+        self.succeeded.len() + self.failed.len()
+    }
+
+    /// Human readable summary, listing every failure.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} of {} files processed", self.succeeded.len(), self.total());
+        if !self.failed.is_empty() {
+            summary.push_str(&format!(", {} failed:", self.failed.len()));
+            for (input, err) in &self.failed {
+                summary.push_str(&format!("\n  {}: {}", input.display(), err));
+            }
+        }
+        summary
+    }
+}
+
+/// Expands every `--batch` source in order; see [`collect_source`].
+///
+/// A source that finds nothing is most likely mistyped, so it is an error
+/// rather than an empty batch that looks like success.
+pub fn collect_inputs(sources: &[String]) -> Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    let mut empty = Vec::new();
+    for source in sources {
+        let found = collect_source(source)?;
+        if found.is_empty() {
+            empty.push(source.clone());
+        }
+        inputs.extend(found);
+    }
+    if !empty.is_empty() {
+        return Err(MirageError::NoInputs { sources: empty });
+    }
+    Ok(inputs)
+}
+
+/// Expands one `--batch` source: a directory yields every image file directly
+/// inside it, anything else is treated as a glob pattern (a plain file path
+/// matches just itself). Paths come back sorted.
+pub fn collect_source(source: &str) -> Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    if Path::new(source).is_dir() {
+        for entry in fs::read_dir(source)? {
+            let path = entry?.path();
+            if path.is_file() && ImageFormat::from_path(&path).is_ok() {
+                inputs.push(path);
+            }
+        }
+    } else {
+        let paths = glob::glob(source).map_err(|err| MirageError::InvalidPattern {
+            pattern: source.to_string(),
+            reason: err.to_string(),
+        })?;
+        for path in paths {
+            let path = path.map_err(|err| MirageError::Io(err.into()))?;
+            if path.is_file() {
+                inputs.push(path);
+            }
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// Builds the output path for `input` inside `output_dir`.
+///
+/// The template may use `{name}` (file name), `{stem}` (file name without
+/// extension) and `{ext}` (extension without the dot).
+pub fn output_path(input: &Path, output_dir: &Path, template: &str) -> PathBuf {
+    let part = |value: Option<&std::ffi::OsStr>| value.map(|v| v.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = template
+        .replace("{name}", &part(input.file_name()))
+        .replace("{stem}", &part(input.file_stem()))
+        .replace("{ext}", &part(input.extension()));
+    output_dir.join(name)
+}
+
+/// Output path of every input, in input order, checked before anything is
+/// written: two inputs may not share an output, or their workers would race
+/// to write the same file, and no output may overwrite an input.
+pub fn plan_outputs(inputs: &[PathBuf], output_dir: &Path, template: &str) -> Result<Vec<PathBuf>> {
+    // Inputs that have vanished can't be overwritten either.
+    let sources: HashMap<PathBuf, &PathBuf> =
+        inputs.iter().filter_map(|input| Some((fs::canonicalize(input).ok()?, input))).collect();
+    let mut planned: HashMap<PathBuf, &PathBuf> = HashMap::new();
+    let mut outputs = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let output = output_path(input, output_dir, template);
+        if let Some(first) = planned.insert(output.clone(), input) {
+            return Err(MirageError::DuplicateOutput { output, first: first.clone(), second: input.clone() });
+        }
+        if let Some(source) = fs::canonicalize(&output).ok().and_then(|output| sources.get(&output)) {
+            return Err(MirageError::OutputIsInput { input: (*source).clone(), output });
+        }
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
+
+/// Decodes `input`, runs the chain over it in memory and writes `output`.
+pub fn process_file(input: &Path, output: &Path, commands: &[ChainCommands]) -> Result<()> {
+    let img = open_image(&input.to_string_lossy())?;
+    if let Some(result) = pipeline::execute(Some(img), commands)? {
+        save_image(&result, &output.to_string_lossy())?;
+    }
+    Ok(())
+}
+
+/// Runs the chain over every input on `jobs` worker threads, carrying on past
+/// failures.
+///
+/// Workers pull paths from a bounded queue and decode, process and save one file
+/// at a time, so no more than `jobs` decoded images are ever held in memory.
+/// Progress is printed in input order regardless of which worker finishes first.
+/// Nothing is written if two inputs would share an output, see [`plan_outputs`].
+pub fn run_batch(
+    inputs: &[PathBuf],
+    output_dir: &Path,
+    template: &str,
+    commands: &[ChainCommands],
+    jobs: usize,
+) -> Result<BatchReport> {
+    let outputs = &plan_outputs(inputs, output_dir, template)?;
+    fs::create_dir_all(output_dir)?;
+    let jobs = jobs.max(1);
+    let (job_tx, job_rx) = channel::bounded::<(usize, (&PathBuf, &PathBuf))>(jobs);
+    let (done_tx, done_rx) = channel::unbounded::<(usize, PathBuf, Result<()>)>();
+    let mut report = BatchReport::default();
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let job_rx = job_rx.clone();
+            let done_tx = done_tx.clone();
+            scope.spawn(move || {
+                for (index, (input, output)) in job_rx {
+                    let result = process_file(input, output, commands);
+                    if done_tx.send((index, output.clone(), result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // Only the workers may hold these, so the channels close once they are done.
+        drop(job_rx);
+        drop(done_tx);
+        scope.spawn(move || {
+            for job in inputs.iter().zip(outputs).enumerate() {
+                if job_tx.send(job).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut finished = BTreeMap::new();
+        for (index, output, result) in done_rx {
+            finished.insert(index, (output, result));
+            while let Some((output, result)) = finished.remove(&report.total()) {
+                let input = inputs[report.total()].clone();
+                let progress = format!("[{}/{}]", report.total() + 1, inputs.len());
+                match result {
+                    Ok(()) => {
+                        println!("{} {} -> {}", progress, input.display(), output.display());
+                        report.succeeded.push((input, output));
+                    }
+                    Err(err) => {
+                        println!("{} {} failed: {}", progress, input.display(), err);
+                        report.failed.push((input, err));
+                    }
+                }
+            }
+        }
+    });
+    Ok(report)
+}
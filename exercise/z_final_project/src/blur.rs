@@ -0,0 +1,254 @@
+//! Blurs with a choice of kernel.
+//!
+//! Every kernel is separable, so an image is blurred by filtering its rows and
+//! then its columns. The box and stack kernels keep running sums along each
+//! line, which makes their cost independent of the radius. Pixels beyond the
+//! edges repeat the nearest edge pixel.
+
+use std::fmt;
+use std::str::FromStr;
+use image::{DynamicImage, ImageBuffer, Pixel, Primitive};
+use num_traits::NumCast;
+
+/// Shape of the blur, all sized to the same standard deviation.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Kernel {
+    /// A true Gaussian, cut off at three standard deviations. Slowest for
+    /// large radii, since every pass weighs the whole kernel.
+    Gaussian,
+    /// Three box blurs in a row, which closely approximate a Gaussian.
+    Box,
+    /// A single triangular (tent) blur; the fastest, but blockier than the
+    /// other two.
+    Stack,
+}
+
+impl FromStr for Kernel {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "gaussian" => Ok(Kernel::Gaussian),
+            "box" => Ok(Kernel::Box),
+            "stack" => Ok(Kernel::Stack),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kernel::Gaussian => "gaussian",
+            Kernel::Box => "box",
+            Kernel::Stack => "stack",
+        })
+    }
+}
+
+/// One pass over a line, from `input` into `output` of the same length.
+type Filter = dyn Fn(&[f64], &mut [f64]);
+
+/// Widths of the three box blurs that together approximate a Gaussian of
+/// standard deviation `sigma` (after Kovesi, "Fast almost-Gaussian filtering").
+fn box_radii(sigma: f64) -> [usize; 3] {
+    let variance = 12.0 * sigma * sigma;
+    let mut lower = (variance / 3.0 + 1.0).sqrt().floor() as i64;
+    if lower % 2 == 0 {
+        lower -= 1;
+    }
+    let lower = lower.max(1);
+    let lower_f = lower as f64;
+    let count = ((variance - 3.0 * lower_f * lower_f - 12.0 * lower_f - 9.0) / (-4.0 * lower_f - 4.0)).round() as i64;
+    [0, 1, 2].map(|pass| {
+        let width = if pass < count { lower } else { lower + 2 };
+        (width as usize - 1) / 2
+    })
+}
+
+/// Radius of the tent whose standard deviation is `sigma`; a tent of radius
+/// `r` has a variance of `r (r + 2) / 6`.
+fn stack_radius(sigma: f64) -> usize {
+    ((6.0 * sigma * sigma + 1.0).sqrt() - 1.0).round().max(0.0) as usize
+}
+
+/// Sum of the samples from `from` to `to`, with positions past the ends
+/// repeating the edge samples; costs no more than the line is long.
+fn clamped_sum(input: &[f64], from: isize, to: isize) -> f64 {
+    let last = input.len() as isize - 1;
+    let before = (to.min(-1) - from + 1).max(0) as f64 * input[0];
+    let after = (to - from.max(last + 1) + 1).max(0) as f64 * input[last as usize];
+    let inside: f64 = input[from.clamp(0, last + 1) as usize..(to + 1).clamp(0, last + 1) as usize].iter().sum();
+    before + inside + after
+}
+
+/// Averages `2 radius + 1` samples around every position.
+fn box_line(radius: usize, input: &[f64], output: &mut [f64]) {
+    let last = input.len() as isize - 1;
+    let at = |i: isize| input[i.clamp(0, last) as usize];
+    let radius = radius as isize;
+    let mut sum = clamped_sum(input, -radius, radius);
+    let scale = 1.0 / (2 * radius + 1) as f64;
+    for (i, out) in output.iter_mut().enumerate() {
+        let i = i as isize;
+        *out = sum * scale;
+        sum += at(i + radius + 1) - at(i - radius);
+    }
+}
+
+/// Weighs the samples around every position by `radius + 1 - distance`.
+///
+/// The weighted sum is kept up to date with the sums of the samples that are
+/// about to lose weight (the center and everything left of it) and those about
+/// to gain it (everything right of the center), as in Mario Klingemann's
+/// stack blur.
+fn stack_line(radius: usize, input: &[f64], output: &mut [f64]) {
+    let last = input.len() as isize - 1;
+    let at = |i: isize| input[i.clamp(0, last) as usize];
+    let radius = radius as isize;
+    // The first weighted sum, with the weights of the repeated edge samples
+    // added up as arithmetic series: `radius` down to 1 left of the center,
+    // and from the first position past the end down to 1 on the right.
+    let (weight, past_end) = (radius as f64, (radius - last).max(0) as f64);
+    let mut sum = weight * (weight + 1.0) / 2.0 * input[0]
+        + past_end * (past_end + 1.0) / 2.0 * input[last as usize]
+        + (0..=radius.min(last)).map(|j| (radius + 1 - j) as f64 * input[j as usize]).sum::<f64>();
+    let mut outgoing = clamped_sum(input, -radius, 0);
+    let mut incoming = clamped_sum(input, 1, radius + 1);
+    let scale = 1.0 / ((radius + 1) as f64 * (radius + 1) as f64);
+    for (i, out) in output.iter_mut().enumerate() {
+        let i = i as isize;
+        *out = sum * scale;
+        sum += incoming - outgoing;
+        outgoing += at(i + 1) - at(i - radius);
+        incoming += at(i + radius + 2) - at(i + 1);
+    }
+}
+
+/// Convolves with a normalized, symmetric kernel given from its center out.
+fn convolve_line(weights: &[f64], input: &[f64], output: &mut [f64]) {
+    let last = input.len() as isize - 1;
+    let at = |i: isize| input[i.clamp(0, last) as usize];
+    for (i, out) in output.iter_mut().enumerate() {
+        let i = i as isize;
+        *out = weights[0] * at(i)
+            + weights[1..]
+                .iter()
+                .enumerate()
+                .map(|(j, weight)| weight * (at(i - j as isize - 1) + at(i + j as isize + 1)))
+                .sum::<f64>();
+    }
+}
+
+/// Right half of a normalized Gaussian, center first, for lines of at most
+/// `length` samples.
+///
+/// Taps `length` or more away from the center only ever read the repeated
+/// edge samples, so their weights are gathered into the tap at `length`. The
+/// weights past it are summed over at most 4096 stretches of the curve, each
+/// weighed at its middle, which keeps huge sigmas cheap.
+fn gaussian_weights(sigma: f64, length: usize) -> Vec<f64> {
+    let radius = (3.0 * sigma).ceil() as usize;
+    let weight = |i: f64| (-(i * i) / (2.0 * sigma * sigma)).exp();
+    let mut weights: Vec<f64> = (0..=radius.min(length)).map(|i| weight(i as f64)).collect();
+    if radius > length {
+        let step = ((radius - length) / 4096).max(1);
+        weights[length] += (length + 1..=radius)
+            .step_by(step)
+            .map(|start| {
+                let end = (start + step - 1).min(radius);
+                (end - start + 1) as f64 * weight((start + end) as f64 / 2.0)
+            })
+            .sum::<f64>();
+    }
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f64>();
+    weights.iter_mut().for_each(|weight| *weight /= total);
+    weights
+}
+
+/// The passes that make up `kernel`, to be run along both axes of lines of at
+/// most `length` samples.
+fn filters(sigma: f64, kernel: Kernel, length: usize) -> Vec<Box<Filter>> {
+    match kernel {
+        Kernel::Gaussian => {
+            let weights = gaussian_weights(sigma, length);
+            vec![Box::new(move |input: &[f64], output: &mut [f64]| convolve_line(&weights, input, output))]
+        }
+        Kernel::Box => box_radii(sigma)
+            .into_iter()
+            .map(|radius| Box::new(move |input: &[f64], output: &mut [f64]| box_line(radius, input, output)) as Box<Filter>)
+            .collect(),
+        Kernel::Stack => {
+            let radius = stack_radius(sigma);
+            vec![Box::new(move |input: &[f64], output: &mut [f64]| stack_line(radius, input, output))]
+        }
+    }
+}
+
+/// Runs `filter` over every line of one axis of interleaved samples.
+///
+/// Sample `i` of line `line` and channel `channel` is at
+/// `line * line_step + i * step + channel`.
+fn run_lines(samples: &mut [f64], lines: usize, length: usize, line_step: usize, step: usize, channels: usize, filter: &Filter) {
+    let (mut input, mut output) = (vec![0.0; length], vec![0.0; length]);
+    for line in 0..lines {
+        for channel in 0..channels {
+            let index = |i: usize| line * line_step + i * step + channel;
+            for (i, sample) in input.iter_mut().enumerate() {
+                *sample = samples[index(i)];
+            }
+            filter(&input, &mut output);
+            for (i, sample) in output.iter().enumerate() {
+                samples[index(i)] = *sample;
+            }
+        }
+    }
+}
+
+fn blur_buffer<P>(buffer: &ImageBuffer<P, Vec<P::Subpixel>>, sigma: f64, kernel: Kernel) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel,
+    P::Subpixel: Primitive,
+{
+    let (width, height) = (buffer.width() as usize, buffer.height() as usize);
+    let channels = P::CHANNEL_COUNT as usize;
+    let mut samples: Vec<f64> = buffer.as_raw().iter().map(|&sample| NumCast::from(sample).unwrap_or(0.0)).collect();
+    for filter in filters(sigma, kernel, width.max(height)) {
+        run_lines(&mut samples, height, width, width * channels, channels, channels, &filter);
+        run_lines(&mut samples, width, height, channels, width * channels, channels, &filter);
+    }
+    // Integer samples are rounded back; float samples (with a maximum of 1)
+    // are kept as they are.
+    let integer = NumCast::from(P::Subpixel::DEFAULT_MAX_VALUE).is_some_and(|max: f64| max > 1.0);
+    let raw = samples
+        .into_iter()
+        .map(|sample| NumCast::from(if integer { sample.round() } else { sample }).unwrap_or(P::Subpixel::DEFAULT_MIN_VALUE))
+        .collect();
+    ImageBuffer::from_raw(buffer.width(), buffer.height(), raw).expect("blurring keeps the number of samples")
+}
+
+/// Sigmas above this blur like it: the kernel is then so much wider than any
+/// image that nearly all of its weight falls on the repeated edge pixels.
+const MAX_SIGMA: f64 = 1e9;
+
+/// Blurs `img` with a kernel of standard deviation `sigma`, keeping its color
+/// type. A `sigma` of 0 leaves the image as it is.
+pub fn blur(img: &DynamicImage, sigma: f64, kernel: Kernel) -> DynamicImage {
+    if sigma <= 0.0 || img.width() == 0 || img.height() == 0 {
+        return img.clone();
+    }
+    let sigma = sigma.min(MAX_SIGMA);
+    match img {
+        DynamicImage::ImageLuma8(buffer) => DynamicImage::ImageLuma8(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageLumaA8(buffer) => DynamicImage::ImageLumaA8(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageRgb8(buffer) => DynamicImage::ImageRgb8(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageRgba8(buffer) => DynamicImage::ImageRgba8(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageLuma16(buffer) => DynamicImage::ImageLuma16(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageLumaA16(buffer) => DynamicImage::ImageLumaA16(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageRgb16(buffer) => DynamicImage::ImageRgb16(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageRgba16(buffer) => DynamicImage::ImageRgba16(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageRgb32F(buffer) => DynamicImage::ImageRgb32F(blur_buffer(buffer, sigma, kernel)),
+        DynamicImage::ImageRgba32F(buffer) => DynamicImage::ImageRgba32F(blur_buffer(buffer, sigma, kernel)),
+        _ => DynamicImage::ImageRgba32F(blur_buffer(&img.to_rgba32f(), sigma, kernel)),
+    }
+}
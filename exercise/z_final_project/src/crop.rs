@@ -0,0 +1,239 @@
+//! Cropping to a rectangle that is checked against the image.
+//!
+//! The image crate's own crop quietly shrinks a rectangle that sticks out of
+//! the image; `crop` reports it instead, so a chain never continues with a
+//! smaller image than asked for.
+
+use std::fmt;
+use std::str::FromStr;
+use image::DynamicImage;
+use crate::{Argument, Arguments, MirageError, Result};
+
+/// A position or size in pixels, or in percent of the image's side.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Length {
+    Pixels(i64),
+    Percent(f64),
+}
+
+impl FromStr for Length {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.strip_suffix('%') {
+            Some(percent) => percent.parse().ok().filter(|percent: &f64| percent.is_finite()).map(Length::Percent).ok_or(()),
+            None => s.parse().map(Length::Pixels).map_err(|_| ()),
+        }
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Pixels(pixels) => write!(f, "{}", pixels),
+            Length::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl Length {
+    /// In pixels, for an image whose side is `side` pixels long.
+    pub fn resolve(&self, side: u32) -> i64 {
+        match *self {
+            Length::Pixels(pixels) => pixels,
+            Length::Percent(percent) => (side as f64 * percent / 100.0).round() as i64,
+        }
+    }
+
+    fn is_negative(&self) -> bool {
+        match *self {
+            Length::Pixels(pixels) => pixels < 0,
+            Length::Percent(percent) => percent < 0.0,
+        }
+    }
+}
+
+/// Where the rectangle sits in the image.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Gravity {
+    /// `x` and `y` give the top-left corner; negative values count from the
+    /// right and bottom edges.
+    None,
+    NorthWest,
+    North,
+    NorthEast,
+    West,
+    Center,
+    East,
+    SouthWest,
+    South,
+    SouthEast,
+}
+
+impl FromStr for Gravity {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Gravity::None),
+            "north-west" => Ok(Gravity::NorthWest),
+            "north" => Ok(Gravity::North),
+            "north-east" => Ok(Gravity::NorthEast),
+            "west" => Ok(Gravity::West),
+            "center" => Ok(Gravity::Center),
+            "east" => Ok(Gravity::East),
+            "south-west" => Ok(Gravity::SouthWest),
+            "south" => Ok(Gravity::South),
+            "south-east" => Ok(Gravity::SouthEast),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Gravity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Gravity::None => "none",
+            Gravity::NorthWest => "north-west",
+            Gravity::North => "north",
+            Gravity::NorthEast => "north-east",
+            Gravity::West => "west",
+            Gravity::Center => "center",
+            Gravity::East => "east",
+            Gravity::SouthWest => "south-west",
+            Gravity::South => "south",
+            Gravity::SouthEast => "south-east",
+        })
+    }
+}
+
+impl Gravity {
+    /// How far along the free space (0 = left or top, 2 = right or bottom, in
+    /// halves) the rectangle is pushed, horizontally and vertically.
+    fn halves(&self) -> (u32, u32) {
+        match self {
+            Gravity::None | Gravity::NorthWest => (0, 0),
+            Gravity::North => (1, 0),
+            Gravity::NorthEast => (2, 0),
+            Gravity::West => (0, 1),
+            Gravity::Center => (1, 1),
+            Gravity::East => (2, 1),
+            Gravity::SouthWest => (0, 2),
+            Gravity::South => (1, 2),
+            Gravity::SouthEast => (2, 2),
+        }
+    }
+}
+
+/// Everything `crop` needs to cut out a rectangle.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CropParams {
+    pub x: Length,
+    pub y: Length,
+    pub width: Length,
+    pub height: Length,
+    /// Aligns the rectangle to a side, corner or the center of the image,
+    /// with `x` and `y` shifting it from there (positive is right and down).
+    pub gravity: Gravity,
+}
+
+impl Default for CropParams {
+    fn default() -> Self {
+        CropParams::pixels(0, 0, 1, 1)
+    }
+}
+
+impl CropParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::optional("x", "0").with_hint("negative counts from the right"),
+        Argument::optional("y", "0").with_hint("negative counts from the bottom"),
+        Argument::required("width"),
+        Argument::required("height"),
+        Argument::optional("gravity", "none")
+            .with_hint("none, north-west, north, north-east, west, center, east, south-west, south, south-east"),
+    ];
+
+    /// A rectangle in pixels, with its top-left corner at `x`, `y`.
+    pub fn pixels(x: i64, y: i64, width: u32, height: u32) -> Self {
+        CropParams {
+            x: Length::Pixels(x),
+            y: Length::Pixels(y),
+            width: Length::Pixels(width as i64),
+            height: Length::Pixels(height as i64),
+            gravity: Gravity::None,
+        }
+    }
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let length = |name: &str| {
+            args.value(name).parse::<Length>().map_err(|_| args.invalid(name, "must be a number of pixels or a percentage like 25%"))
+        };
+        let size = |name: &str| {
+            length(name).and_then(|size| match size {
+                Length::Pixels(pixels) if pixels > 0 => Ok(size),
+                Length::Percent(percent) if percent > 0.0 => Ok(size),
+                _ => Err(args.invalid(name, "must be positive")),
+            })
+        };
+        Ok(CropParams {
+            x: length("x")?,
+            y: length("y")?,
+            width: size("width")?,
+            height: size("height")?,
+            gravity: args.value("gravity").parse().map_err(|_| {
+                args.invalid("gravity", "must be one of none, north-west, north, north-east, west, center, east, south-west, south, south-east")
+            })?,
+        })
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![
+            self.x.to_string(),
+            self.y.to_string(),
+            self.width.to_string(),
+            self.height.to_string(),
+            self.gravity.to_string(),
+        ]
+    }
+
+    /// The rectangle as `(x, y, width, height)` in an image of `width` by
+    /// `height` pixels, or an error if it doesn't fit.
+    pub fn rectangle(&self, width: u32, height: u32) -> Result<(u32, u32, u32, u32)> {
+        // Percentages of tiny images still cut out at least a pixel.
+        let crop_width = self.width.resolve(width).max(1);
+        let crop_height = self.height.resolve(height).max(1);
+        // Offsets and sizes far outside the image saturate instead of
+        // overflowing; they are out of bounds either way.
+        let place = |offset: &Length, side: u32, crop_side: i64, halves: u32| {
+            let shift = offset.resolve(side);
+            if self.gravity == Gravity::None && offset.is_negative() {
+                (side as i64).saturating_add(shift)
+            } else {
+                ((side as i64 - crop_side).saturating_mul(halves as i64) / 2).saturating_add(shift)
+            }
+        };
+        let fits = |start: i64, crop_side: i64, side: u32| {
+            start >= 0 && start.checked_add(crop_side).is_some_and(|end| end <= side as i64)
+        };
+        let (horizontal, vertical) = self.gravity.halves();
+        let x = place(&self.x, width, crop_width, horizontal);
+        let y = place(&self.y, height, crop_height, vertical);
+        if !fits(x, crop_width, width) || !fits(y, crop_height, height) {
+            return Err(MirageError::CropOutOfBounds {
+                x,
+                y,
+                width: crop_width,
+                height: crop_height,
+                image_width: width,
+                image_height: height,
+            });
+        }
+        Ok((x as u32, y as u32, crop_width as u32, crop_height as u32))
+    }
+}
+
+pub fn crop(img: &DynamicImage, params: &CropParams) -> Result<DynamicImage> {
+    let (x, y, width, height) = params.rectangle(img.width(), img.height())?;
+    Ok(img.crop_imm(x, y, width, height))
+}
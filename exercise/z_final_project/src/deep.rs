@@ -0,0 +1,363 @@
+//! Deep zooms by perturbation.
+//!
+//! Past a zoom of about 1e12 neighbouring pixels are closer together than
+//! `f64` can tell apart. Instead of iterating every pixel on its own, one
+//! reference orbit through the center of the image is computed with
+//! fixed-point numbers as precise as the zoom needs, and every pixel only
+//! iterates its (tiny) difference to that orbit, which `f64` holds fine.
+//!
+//! When a pixel's orbit strays from the reference (or the reference escapes
+//! first) its difference is rebased onto the start of the reference orbit, so
+//! a single reference is enough for the whole image.
+
+use std::cmp::Ordering;
+use num_complex::Complex;
+use crate::fractal::{finish_escape, Family, FractalParams};
+
+/// Signed fixed-point number: a magnitude of 32-bit limbs (least significant
+/// first) of which the lowest `frac` limbs lie after the binary point.
+#[derive(Clone, Debug)]
+pub struct Fixed {
+    negative: bool,
+    limbs: Vec<u32>,
+    frac: usize,
+}
+
+impl Fixed {
+    pub fn zero(frac: usize) -> Self {
+        Fixed { negative: false, limbs: Vec::new(), frac }
+    }
+
+    /// The exact value of `value`, truncated to `frac` limbs after the point.
+    pub fn from_f64(value: f64, frac: usize) -> Self {
+        let bits = value.abs().to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        let mantissa = bits & ((1 << 52) - 1);
+        let (mantissa, exponent) = match exponent {
+            0 => (mantissa, 1 - 1075),
+            _ => (mantissa | 1 << 52, exponent - 1075),
+        };
+        let shift = exponent + 32 * frac as i64;
+        let limbs = if shift >= 0 {
+            let (whole, bits) = ((shift / 32) as usize, (shift % 32) as u32);
+            let wide = (mantissa as u128) << bits;
+            let mut limbs = vec![0; whole];
+            limbs.extend([wide as u32, (wide >> 32) as u32, (wide >> 64) as u32]);
+            limbs
+        } else if shift > -64 {
+            let narrow = mantissa >> -shift;
+            vec![narrow as u32, (narrow >> 32) as u32]
+        } else {
+            Vec::new()
+        };
+        Fixed { negative: value < 0.0, limbs, frac }.trimmed()
+    }
+
+    /// The decimal number `text` (like `-1.7499e-2`), truncated to `frac`
+    /// limbs after the point, without rounding to `f64` on the way. `None` if
+    /// `text` is not such a number or is beyond 1e400.
+    pub fn parse(text: &str, frac: usize) -> Option<Self> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = whole.bytes().chain(fraction.bytes());
+        if whole.len() + fraction.len() == 0 || !digits.clone().all(|digit| digit.is_ascii_digit()) {
+            return None;
+        }
+        // All digits as one integer, shifted up by `frac` limbs and then scaled
+        // by the power of ten, so only the final division truncates.
+        let integer = digits.fold(Vec::new(), |integer, digit| multiply_add_small(&integer, 10, (digit - b'0') as u32));
+        let mut limbs = [vec![0; frac], integer].concat();
+        let scale = exponent.checked_sub(fraction.len() as i64)?;
+        if scale > 400 {
+            return None;
+        }
+        for _ in 0..scale.max(0) {
+            limbs = multiply_add_small(&limbs, 10, 0);
+        }
+        for _ in scale..0 {
+            if limbs.iter().all(|&limb| limb == 0) {
+                break;
+            }
+            limbs = divide_small(&limbs, 10);
+        }
+        Some(Fixed { negative, limbs, frac }.trimmed())
+    }
+
+    /// Nearest `f64` (up to rounding of the lowest bits).
+    pub fn to_f64(&self) -> f64 {
+        let magnitude: f64 = self
+            .limbs
+            .iter()
+            .enumerate()
+            .map(|(i, &limb)| limb as f64 * 2f64.powi(32 * (i as i32 - self.frac as i32)))
+            .sum();
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    pub fn add(&self, other: &Fixed) -> Fixed {
+        if self.negative == other.negative {
+            return Fixed { negative: self.negative, limbs: add_magnitudes(&self.limbs, &other.limbs), frac: self.frac };
+        }
+        match compare_magnitudes(&self.limbs, &other.limbs) {
+            Ordering::Less => Fixed {
+                negative: other.negative,
+                limbs: subtract_magnitudes(&other.limbs, &self.limbs),
+                frac: self.frac,
+            },
+            _ => Fixed {
+                negative: self.negative,
+                limbs: subtract_magnitudes(&self.limbs, &other.limbs),
+                frac: self.frac,
+            },
+        }
+        .trimmed()
+    }
+
+    pub fn neg(&self) -> Fixed {
+        Fixed { negative: !self.negative && !self.limbs.is_empty(), ..self.clone() }
+    }
+
+    pub fn sub(&self, other: &Fixed) -> Fixed {
+        self.add(&other.neg())
+    }
+
+    /// Product, truncated to `frac` limbs after the point.
+    pub fn mul(&self, other: &Fixed) -> Fixed {
+        let mut product = vec![0u32; self.limbs.len() + other.limbs.len() + 1];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let sum = product[i + j] as u64 + a as u64 * b as u64 + carry;
+                product[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            product[i + other.limbs.len()] = carry as u32;
+        }
+        let limbs = product.split_off(self.frac.min(product.len()));
+        Fixed { negative: self.negative != other.negative, limbs, frac: self.frac }.trimmed()
+    }
+
+    fn trimmed(mut self) -> Fixed {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        if self.limbs.is_empty() {
+            self.negative = false;
+        }
+        self
+    }
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, &limb) in long.iter().enumerate() {
+        let total = limb as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+        sum.push(total as u32);
+        carry = total >> 32;
+    }
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+/// `a * factor + addend`.
+fn multiply_add_small(a: &[u32], factor: u32, addend: u32) -> Vec<u32> {
+    let mut product = Vec::with_capacity(a.len() + 1);
+    let mut carry = addend as u64;
+    for &limb in a {
+        let total = limb as u64 * factor as u64 + carry;
+        product.push(total as u32);
+        carry = total >> 32;
+    }
+    if carry > 0 {
+        product.push(carry as u32);
+    }
+    product
+}
+
+/// `a / divisor`, rounded down.
+fn divide_small(a: &[u32], divisor: u32) -> Vec<u32> {
+    let mut quotient = vec![0; a.len()];
+    let mut remainder = 0u64;
+    for (i, &limb) in a.iter().enumerate().rev() {
+        let total = remainder << 32 | limb as u64;
+        quotient[i] = (total / divisor as u64) as u32;
+        remainder = total % divisor as u64;
+    }
+    quotient
+}
+
+/// `a - b` for `a >= b`.
+fn subtract_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut total = limb as i64 - b.get(i).copied().unwrap_or(0) as i64 - borrow;
+        borrow = (total < 0) as i64;
+        if total < 0 {
+            total += 1 << 32;
+        }
+        difference.push(total as u32);
+    }
+    difference
+}
+
+/// Both magnitudes must be trimmed.
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+/// Limbs after the point needed to tell the pixels of `params` apart, with
+/// 64 bits to spare for the error that builds up along the orbit.
+pub fn precision_limbs(params: &FractalParams) -> usize {
+    let pixels_per_unit = 1.0 / params.pixel_size();
+    let bits = pixels_per_unit.log2().max(0.0) + 64.0;
+    (bits / 32.0).ceil() as usize
+}
+
+#[derive(Clone, Debug)]
+struct FixedComplex {
+    re: Fixed,
+    im: Fixed,
+}
+
+impl FixedComplex {
+    fn from_complex(value: Complex<f64>, frac: usize) -> Self {
+        FixedComplex { re: Fixed::from_f64(value.re, frac), im: Fixed::from_f64(value.im, frac) }
+    }
+
+    fn to_complex(&self) -> Complex<f64> {
+        Complex::new(self.re.to_f64(), self.im.to_f64())
+    }
+
+    fn add(&self, other: &FixedComplex) -> FixedComplex {
+        FixedComplex { re: self.re.add(&other.re), im: self.im.add(&other.im) }
+    }
+
+    fn mul(&self, other: &FixedComplex) -> FixedComplex {
+        FixedComplex {
+            re: self.re.mul(&other.re).sub(&self.im.mul(&other.im)),
+            im: self.re.mul(&other.im).add(&self.im.mul(&other.re)),
+        }
+    }
+
+    fn conj(&self) -> FixedComplex {
+        FixedComplex { re: self.re.clone(), im: self.im.neg() }
+    }
+
+    fn fold(&self) -> FixedComplex {
+        let abs = |value: &Fixed| if value.negative { value.neg() } else { value.clone() };
+        FixedComplex { re: abs(&self.re), im: abs(&self.im) }
+    }
+}
+
+/// The orbit of the image center, computed with [`precision_limbs`] of
+/// precision and rounded to `f64`. It ends after `params.iterations` steps
+/// or at the first point that escapes, but always holds at least two points.
+pub fn reference_orbit(params: &FractalParams) -> Vec<Complex<f64>> {
+    let frac = precision_limbs(params);
+    // The center as written, when it was kept, is exact where `f64` rounds.
+    let center = params
+        .center_digits
+        .as_ref()
+        .and_then(|(re, im)| Some(FixedComplex { re: Fixed::parse(re, frac)?, im: Fixed::parse(im, frac)? }))
+        .unwrap_or_else(|| FixedComplex::from_complex(params.center, frac));
+    let (mut z, c) = match params.family {
+        Family::Julia => (center, FixedComplex::from_complex(params.c, frac)),
+        _ => (FixedComplex { re: Fixed::zero(frac), im: Fixed::zero(frac) }, center),
+    };
+    let bailout = bailout(params);
+    let mut orbit = vec![z.to_complex()];
+    while orbit.len() < 2 || (orbit.len() <= params.iterations as usize && orbit[orbit.len() - 1].norm() <= bailout) {
+        z = match params.family {
+            Family::Julia | Family::Mandelbrot => z.mul(&z),
+            Family::BurningShip => {
+                let folded = z.fold();
+                folded.mul(&folded)
+            }
+            Family::Tricorn => z.conj().mul(&z.conj()),
+            Family::Multibrot => (1..params.exponent).fold(z.clone(), |power, _| power.mul(&z)),
+        }
+        .add(&c);
+        orbit.push(z.to_complex());
+    }
+    orbit
+}
+
+fn bailout(params: &FractalParams) -> f64 {
+    if params.smooth {
+        256.0
+    } else {
+        2.0
+    }
+}
+
+/// `f(reference + delta) - f(reference)` for the iteration of `params`, without
+/// the `+ c` and without cancellation for tiny `delta`.
+fn delta_step(params: &FractalParams, reference: Complex<f64>, delta: Complex<f64>) -> Complex<f64> {
+    match params.family {
+        Family::Julia | Family::Mandelbrot => delta * (reference * 2.0 + delta),
+        Family::Tricorn => {
+            let (reference, delta) = (reference.conj(), delta.conj());
+            delta * (reference * 2.0 + delta)
+        }
+        Family::Multibrot => {
+            // Binomial expansion of (Z + d)^n - Z^n.
+            let n = params.exponent;
+            let mut sum = Complex::new(0.0, 0.0);
+            let mut binomial = 1.0;
+            for k in 1..=n {
+                binomial *= (n - k + 1) as f64 / k as f64;
+                sum += reference.powu(n - k) * delta.powu(k) * binomial;
+            }
+            sum
+        }
+        // The absolute values don't expand; fine at shallow zooms only.
+        Family::BurningShip => {
+            let fold = |z: Complex<f64>| Complex::new(z.re.abs(), z.im.abs());
+            let (full, reference) = (fold(reference + delta), fold(reference));
+            full * full - reference * reference
+        }
+    }
+}
+
+/// Escape-time value, as [`crate::fractal::escape_time`] gives it, of the
+/// point `offset` away from the image center, using the center's `orbit`.
+pub fn escape_time(offset: Complex<f64>, orbit: &[Complex<f64>], params: &FractalParams) -> Option<f64> {
+    let bailout = bailout(params);
+    let zero = Complex::new(0.0, 0.0);
+    let (mut delta, delta_c) = match params.family {
+        Family::Julia => (offset, zero),
+        _ => (zero, offset),
+    };
+    let mut index = 0;
+    let mut z = orbit[0] + delta;
+    let mut n = 0;
+    while n < params.iterations && z.norm() <= bailout {
+        delta = delta_step(params, orbit[index], delta) + delta_c;
+        index += 1;
+        z = orbit[index] + delta;
+        n += 1;
+        // Rebase when the reference runs out or the pixel's orbit has come
+        // closer to the critical point than to the reference.
+        if index == orbit.len() - 1 || z.norm() < delta.norm() {
+            delta = z - orbit[0];
+            index = 0;
+        }
+    }
+    finish_escape(z.norm(), n, params)
+}
@@ -0,0 +1,110 @@
+use std::f64::consts::FRAC_2_PI;
+use image::{DynamicImage, RgbImage};
+use num_complex::Complex;
+use crate::expression::Expression;
+use crate::fractal::{format_complex, render_parallel, Viewport};
+use crate::palette::hsv_to_rgb;
+use crate::{Argument, Arguments, Generator, Result};
+
+/// Everything the `domain` generator needs to render an image.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DomainParams {
+    pub viewport: Viewport,
+    /// The function of `z` to plot.
+    pub expression: Expression,
+    /// Darken the image in rings where the modulus doubles.
+    pub contours: bool,
+    /// Worker threads, 0 means one per CPU.
+    pub threads: usize,
+}
+
+/// Plotted when no expression is given: two zeros, a double zero and two poles.
+const DEFAULT_EXPRESSION: &str = "(z^2-1)*(z-2-i)^2/(z^2+2+2i)";
+
+impl Default for DomainParams {
+    fn default() -> Self {
+        DomainParams {
+            viewport: Viewport { width: 800, height: 800, center: Complex::new(0.0, 0.0), zoom: 0.5 },
+            expression: DEFAULT_EXPRESSION.parse().expect("default expression parses"),
+            contours: false,
+            threads: 0,
+        }
+    }
+}
+
+impl Generator for DomainParams {
+    fn size(&self) -> (u32, u32) {
+        (self.viewport.width, self.viewport.height)
+    }
+
+    fn generate(&self) -> Result<DynamicImage> {
+        Ok(DynamicImage::ImageRgb8(render(self)))
+    }
+}
+
+impl DomainParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::optional("width", "800"),
+        Argument::optional("height", "800"),
+        Argument::optional("expression", DEFAULT_EXPRESSION)
+            .with_hint("in z with + - * / ^ sin cos exp log, like z^3-1"),
+        Argument::optional("center", "0+0i"),
+        Argument::optional("zoom", "0.5"),
+        Argument::optional("contours", "false"),
+        Argument::optional("threads", "0").with_hint("0 = one per CPU"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let expression = args
+            .value("expression")
+            .parse()
+            .map_err(|reason: String| args.invalid("expression", &reason))?;
+        Ok(DomainParams {
+            viewport: Viewport::from_arguments(args)?,
+            expression,
+            contours: args.value("contours").parse().map_err(|_| args.invalid("contours", "must be true or false"))?,
+            threads: args.number("threads")?,
+        })
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![
+            self.viewport.width.to_string(),
+            self.viewport.height.to_string(),
+            self.expression.to_string(),
+            format_complex(self.viewport.center),
+            self.viewport.zoom.to_string(),
+            self.contours.to_string(),
+            self.threads.to_string(),
+        ]
+    }
+}
+
+/// Color of the value `w`: the hue follows its argument (red for positive
+/// reals, cyan for negative ones) and the lightness its modulus, from black
+/// at zeros through full color at modulus 1 to white at poles.
+pub fn color(w: Complex<f64>, contours: bool) -> [u8; 3] {
+    if w.re.is_nan() || w.im.is_nan() {
+        return [128, 128, 128];
+    }
+    let modulus = w.norm();
+    if modulus.is_infinite() {
+        return [255, 255, 255];
+    }
+    let hue = w.arg().to_degrees();
+    let lightness = FRAC_2_PI * modulus.atan();
+    // HSL with full saturation, expressed in HSV.
+    let (saturation, value) = if lightness <= 0.5 { (1.0, 2.0 * lightness) } else { (2.0 - 2.0 * lightness, 1.0) };
+    let shade = if contours && modulus > 0.0 { 0.7 + 0.3 * modulus.log2().rem_euclid(1.0) } else { 1.0 };
+    hsv_to_rgb(hue, saturation, value * shade)
+}
+
+/// Renders the domain coloring of the expression over the viewport.
+pub fn render(params: &DomainParams) -> RgbImage {
+    let viewport = &params.viewport;
+    render_parallel(viewport.width, viewport.height, params.threads, |x, y| {
+        let z = viewport.point(x as f64 + 0.5, y as f64 + 0.5);
+        color(params.expression.eval(z), params.contours)
+    })
+}
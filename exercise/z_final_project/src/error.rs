@@ -0,0 +1,190 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use image::ImageError;
+
+pub type Result<T> = std::result::Result<T, MirageError>;
+
+/// Everything that can go wrong while parsing or running a mirage chain.
+#[derive(Debug)]
+pub enum MirageError {
+    /// The chain contains a keyword no operation answers to.
+    UnknownCommand(String),
+    /// A command was given too few or too many arguments.
+    WrongArity { command: String, usage: String },
+    /// An argument that should be a number could not be parsed as one.
+    BadNumber { command: String, value: String },
+    /// `rotate` was given an angle that is not a finite number.
+    InvalidRotation(String),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The input file is not an image we can decode.
+    Decode(ImageError),
+    /// The result could not be encoded to the output format.
+    Encode(ImageError),
+    /// A line of a pipeline script could not be parsed.
+    Script { line: usize, source: Box<MirageError> },
+    /// `--batch` was given a malformed glob pattern.
+    InvalidPattern { pattern: String, reason: String },
+    /// An argument parsed but is outside what the operation accepts.
+    InvalidArgument { command: String, value: String, reason: String },
+    /// Some files of a batch could not be processed.
+    BatchFailed { failed: usize, total: usize },
+    /// A generator is not the first command of a chain without an input
+    /// image; `position` counts from 1.
+    MisplacedGenerator { command: String, position: usize },
+    /// A `crop` rectangle reaches outside the image it is applied to.
+    CropOutOfBounds { x: i64, y: i64, width: i64, height: i64, image_width: u32, image_height: u32 },
+    /// Two inputs of a batch would be written to the same output file.
+    DuplicateOutput { output: PathBuf, first: PathBuf, second: PathBuf },
+    /// A batch output would overwrite one of the inputs.
+    OutputIsInput { input: PathBuf, output: PathBuf },
+    /// `--batch` sources, directories or patterns, that matched no files.
+    NoInputs { sources: Vec<String> },
+}
+
+impl MirageError {
+    /// Process exit code for the binary; every variant gets its own, starting
+    /// at 3 so none is mistaken for clap rejecting the command line (2):
+    ///
+    /// | code | error |
+    /// |------|-------|
+    /// | 3 | unknown command in the chain |
+    /// | 4 | wrong number of arguments |
+    /// | 5 | argument is not a number |
+    /// | 6 | invalid rotation angle |
+    /// | 7 | I/O error |
+    /// | 8 | input could not be decoded |
+    /// | 9 | output could not be encoded |
+    /// | 10 | error in a pipeline script |
+    /// | 11 | malformed `--batch` pattern |
+    /// | 12 | some files of a batch failed |
+    /// | 13 | invalid argument |
+    /// | 14 | generator not first in the chain |
+    /// | 15 | crop rectangle outside the image |
+    /// | 16 | two batch inputs share an output |
+    /// | 17 | batch output would overwrite an input |
+    /// | 18 | a `--batch` source matched nothing |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MirageError::UnknownCommand(_) => 3,
+            MirageError::WrongArity { .. } => 4,
+            MirageError::BadNumber { .. } => 5,
+            MirageError::InvalidRotation(_) => 6,
+            MirageError::Io(_) => 7,
+            MirageError::Decode(_) => 8,
+            MirageError::Encode(_) => 9,
+            MirageError::Script { .. } => 10,
+            MirageError::InvalidPattern { .. } => 11,
+            MirageError::BatchFailed { .. } => 12,
+            MirageError::InvalidArgument { .. } => 13,
+            MirageError::MisplacedGenerator { .. } => 14,
+            MirageError::CropOutOfBounds { .. } => 15,
+            MirageError::DuplicateOutput { .. } => 16,
+            MirageError::OutputIsInput { .. } => 17,
+            MirageError::NoInputs { .. } => 18,
+        }
+    }
+
+    /// Whether the error comes from a malformed command line, in which case
+    /// printing the help text is useful.
+    pub fn is_usage_error(&self) -> bool {
+        matches!(
+            self,
+            MirageError::UnknownCommand(_) | MirageError::WrongArity { .. } | MirageError::MisplacedGenerator { .. }
+        )
+    }
+
+    /// Classifies an error returned by `image::open`.
+    pub fn from_decode(err: ImageError) -> Self {
+        match err {
+            ImageError::IoError(err) => MirageError::Io(err),
+            err => MirageError::Decode(err),
+        }
+    }
+
+    /// Classifies an error returned by `DynamicImage::save`.
+    pub fn from_encode(err: ImageError) -> Self {
+        match err {
+            ImageError::IoError(err) => MirageError::Io(err),
+            err => MirageError::Encode(err),
+        }
+    }
+}
+
+impl fmt::Display for MirageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MirageError::UnknownCommand(command) => write!(f, "unknown command '{}'", command),
+            MirageError::WrongArity { command, usage } if usage.is_empty() => {
+                write!(f, "wrong number of arguments, usage: {}", command)
+            }
+            MirageError::WrongArity { command, usage } => {
+                write!(f, "wrong number of arguments, usage: {} {}", command, usage)
+            }
+            MirageError::BadNumber { command, value } => {
+                write!(f, "argument '{}' of {} must be a number", value, command)
+            }
+            MirageError::InvalidRotation(value) => {
+                write!(f, "invalid rotation '{}', must be an angle in degrees", value)
+            }
+            MirageError::Io(err) => write!(f, "I/O error: {}", err),
+            MirageError::Decode(err) => write!(f, "failed to decode INFILE: {}", err),
+            MirageError::Encode(err) => write!(f, "failed to encode OUTFILE: {}", err),
+            MirageError::Script { line, source } => write!(f, "script line {}: {}", line, source),
+            MirageError::InvalidPattern { pattern, reason } => {
+                write!(f, "invalid pattern '{}': {}", pattern, reason)
+            }
+            MirageError::InvalidArgument { command, value, reason } => {
+                write!(f, "invalid argument '{}' of {}: {}", value, command, reason)
+            }
+            MirageError::BatchFailed { failed, total } => {
+                write!(f, "{} of {} files failed", failed, total)
+            }
+            MirageError::MisplacedGenerator { command, position } => write!(
+                f,
+                "{} (command {}) generates a new image and would discard the one before it; \
+                 generators must come first in a chain without an input image",
+                command, position
+            ),
+            MirageError::CropOutOfBounds { x, y, width, height, image_width, image_height } => write!(
+                f,
+                "crop rectangle {}x{} at ({}, {}) does not fit in the {}x{} image",
+                width, height, x, y, image_width, image_height
+            ),
+            MirageError::DuplicateOutput { output, first, second } => write!(
+                f,
+                "{} and {} would both be written to {}; use a --template that tells them apart",
+                first.display(),
+                second.display(),
+                output.display()
+            ),
+            MirageError::OutputIsInput { input, output } => write!(
+                f,
+                "{} would overwrite the input {}; use another output directory or --template",
+                output.display(),
+                input.display()
+            ),
+            MirageError::NoInputs { sources } => {
+                write!(f, "no image files found in --batch {}", sources.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for MirageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MirageError::Io(err) => Some(err),
+            MirageError::Decode(err) | MirageError::Encode(err) => Some(err),
+            MirageError::Script { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MirageError {
+    fn from(err: io::Error) -> Self {
+        MirageError::Io(err)
+    }
+}
@@ -0,0 +1,235 @@
+//! A small parser and evaluator for complex functions of `z`.
+//!
+//! Supported syntax: numbers, `z`, `i`, `pi`, `e`, the binary operators
+//! `+ - * / ^` (`^` binds tightest and is right-associative), unary minus,
+//! parentheses, the functions `sin`, `cos`, `exp` and `log` (principal
+//! branch), and implicit multiplication such as `2z` or `3(z+1)`.
+
+use std::f64::consts::{E, PI};
+use std::fmt;
+use std::str::FromStr;
+use num_complex::Complex;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum Function {
+    Sin,
+    Cos,
+    Exp,
+    Log,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Node {
+    Constant(Complex<f64>),
+    Z,
+    Negate(Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Subtract(Box<Node>, Box<Node>),
+    Multiply(Box<Node>, Box<Node>),
+    Divide(Box<Node>, Box<Node>),
+    Power(Box<Node>, Box<Node>),
+    Call(Function, Box<Node>),
+}
+
+impl Node {
+    fn eval(&self, z: Complex<f64>) -> Complex<f64> {
+        match self {
+            Node::Constant(value) => *value,
+            Node::Z => z,
+            Node::Negate(a) => -a.eval(z),
+            Node::Add(a, b) => a.eval(z) + b.eval(z),
+            Node::Subtract(a, b) => a.eval(z) - b.eval(z),
+            Node::Multiply(a, b) => a.eval(z) * b.eval(z),
+            Node::Divide(a, b) => a.eval(z) / b.eval(z),
+            Node::Power(base, exponent) => {
+                let base = base.eval(z);
+                let exponent = exponent.eval(z);
+                // Whole exponents are exact and well defined at 0, unlike powc.
+                if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= i32::MAX as f64 {
+                    base.powi(exponent.re as i32)
+                } else {
+                    base.powc(exponent)
+                }
+            }
+            Node::Call(function, a) => {
+                let a = a.eval(z);
+                match function {
+                    Function::Sin => a.sin(),
+                    Function::Cos => a.cos(),
+                    Function::Exp => a.exp(),
+                    Function::Log => a.ln(),
+                }
+            }
+        }
+    }
+}
+
+/// A parsed complex function of `z`.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    /// The source with whitespace removed, so it stays a single word.
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn eval(&self, z: Complex<f64>) -> Complex<f64> {
+        self.root.eval(z)
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    /// Parses an expression; the error says what went wrong and where.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source: String = s.chars().filter(|ch| !ch.is_whitespace()).collect();
+        let mut parser = Parser { chars: source.chars().collect(), position: 0 };
+        let root = parser.expression()?;
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("unexpected character"));
+        }
+        Ok(Expression { source, root })
+    }
+}
+
+/// Recursive descent parser over the whitespace-free source.
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        match self.peek() {
+            Some(ch) => format!("{} '{}' at position {}", message, ch, self.position + 1),
+            None => format!("{} at end of expression", message),
+        }
+    }
+
+    /// expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        loop {
+            if self.eat('+') {
+                node = Node::Add(Box::new(node), Box::new(self.term()?));
+            } else if self.eat('-') {
+                node = Node::Subtract(Box::new(node), Box::new(self.term()?));
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    /// term := unary (('*' | '/')? unary)*, juxtaposition meaning '*'
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            if self.eat('*') {
+                node = Node::Multiply(Box::new(node), Box::new(self.unary()?));
+            } else if self.eat('/') {
+                node = Node::Divide(Box::new(node), Box::new(self.unary()?));
+            } else if matches!(self.peek(), Some(ch) if ch == '(' || ch == '.' || ch.is_ascii_alphanumeric()) {
+                node = Node::Multiply(Box::new(node), Box::new(self.power()?));
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    /// unary := '-' unary | power
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat('-') {
+            Ok(Node::Negate(Box::new(self.unary()?)))
+        } else {
+            self.power()
+        }
+    }
+
+    /// power := primary ('^' unary)?
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.primary()?;
+        if self.eat('^') {
+            Ok(Node::Power(Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// primary := number | name | name '(' expression ')' | '(' expression ')'
+    fn primary(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let node = self.expression()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(node)
+            }
+            Some(ch) if ch.is_ascii_digit() || ch == '.' => {
+                let start = self.position;
+                while matches!(self.peek(), Some(ch) if ch.is_ascii_digit() || ch == '.') {
+                    self.position += 1;
+                }
+                let number: String = self.chars[start..self.position].iter().collect();
+                number.parse().map(|value| Node::Constant(Complex::new(value, 0.0))).map_err(|_| {
+                    self.position = start;
+                    self.error("malformed number")
+                })
+            }
+            Some(ch) if ch.is_ascii_alphabetic() => {
+                let start = self.position;
+                let name = ["sin", "cos", "exp", "log", "pi", "z", "i", "e"]
+                    .into_iter()
+                    .find(|name| self.chars[start..].starts_with(&name.chars().collect::<Vec<_>>()))
+                    .ok_or_else(|| self.error("unknown name"))?;
+                self.position += name.len();
+                let function = match name {
+                    "sin" => Function::Sin,
+                    "cos" => Function::Cos,
+                    "exp" => Function::Exp,
+                    "log" => Function::Log,
+                    "pi" => return Ok(Node::Constant(Complex::new(PI, 0.0))),
+                    "e" => return Ok(Node::Constant(Complex::new(E, 0.0))),
+                    "i" => return Ok(Node::Constant(Complex::new(0.0, 1.0))),
+                    _ => return Ok(Node::Z),
+                };
+                if !self.eat('(') {
+                    return Err(self.error("expected '(' after function name"));
+                }
+                let argument = self.expression()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(Node::Call(function, Box::new(argument)))
+            }
+            _ => Err(self.error("expected a number, name or '('")),
+        }
+    }
+}
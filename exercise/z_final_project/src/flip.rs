@@ -0,0 +1,79 @@
+//! Mirroring, and the eight EXIF orientations built from mirrors and quarter
+//! turns.
+
+use std::fmt;
+use std::str::FromStr;
+use image::DynamicImage;
+use crate::{MirageError, Result, Rotation};
+
+/// Which way `flip` mirrors an image.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Axis {
+    /// Left to right.
+    Horizontal,
+    /// Top to bottom.
+    Vertical,
+}
+
+impl FromStr for Axis {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "h" => Ok(Axis::Horizontal),
+            "v" => Ok(Axis::Vertical),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Axis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Axis::Horizontal => "h",
+            Axis::Vertical => "v",
+        })
+    }
+}
+
+pub fn flip(img: &DynamicImage, axis: Axis) -> DynamicImage {
+    match axis {
+        Axis::Horizontal => img.fliph(),
+        Axis::Vertical => img.flipv(),
+    }
+}
+
+/// Mirrors along the diagonal from the top-left corner, so rows become
+/// columns.
+pub fn transpose(img: &DynamicImage) -> DynamicImage {
+    Rotation::Ninety.apply(img).fliph()
+}
+
+/// Mirrors along the diagonal from the top-right corner.
+pub fn transverse(img: &DynamicImage) -> DynamicImage {
+    Rotation::TwoSeventy.apply(img).fliph()
+}
+
+/// Turns an image stored with EXIF orientation `orientation` (1 to 8) upright.
+///
+/// Built from mirrors and [`Rotation`]s, the exact quarter turns `rotate` also
+/// takes for multiples of 90 degrees, so no orientation resamples the image.
+pub fn orient(img: &DynamicImage, orientation: u8) -> Result<DynamicImage> {
+    Ok(match orientation {
+        1 => img.clone(),
+        2 => img.fliph(),
+        3 => Rotation::OneEighty.apply(img),
+        4 => img.flipv(),
+        5 => transpose(img),
+        6 => Rotation::Ninety.apply(img),
+        7 => transverse(img),
+        8 => Rotation::TwoSeventy.apply(img),
+        _ => {
+            return Err(MirageError::InvalidArgument {
+                command: "orient".to_string(),
+                value: orientation.to_string(),
+                reason: "must be between 1 and 8".to_string(),
+            })
+        }
+    })
+}
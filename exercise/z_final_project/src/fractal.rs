@@ -0,0 +1,571 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use image::{DynamicImage, RgbImage};
+use num_complex::Complex;
+use num_traits::Float;
+use crate::deep;
+use crate::palette::Palette;
+use crate::rng;
+use crate::{Argument, Arguments, Generator, Result};
+
+/// Escape-time fractal families the `fractal` generator can render.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Family {
+    /// `z = z^2 + c` with the pixel as starting `z` and a fixed `c`.
+    Julia,
+    /// `z = z^2 + c` starting at 0 with the pixel as `c`.
+    Mandelbrot,
+    /// Like Mandelbrot, but the parts of `z` are made positive before squaring.
+    BurningShip,
+    /// Like Mandelbrot, but `z` is conjugated before squaring.
+    Tricorn,
+    /// `z = z^n + c` starting at 0 with the pixel as `c`.
+    Multibrot,
+}
+
+impl FromStr for Family {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "julia" => Ok(Family::Julia),
+            "mandelbrot" => Ok(Family::Mandelbrot),
+            "burning-ship" => Ok(Family::BurningShip),
+            "tricorn" => Ok(Family::Tricorn),
+            "multibrot" => Ok(Family::Multibrot),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Family::Julia => "julia",
+            Family::Mandelbrot => "mandelbrot",
+            Family::BurningShip => "burning-ship",
+            Family::Tricorn => "tricorn",
+            Family::Multibrot => "multibrot",
+        })
+    }
+}
+
+/// Where the sub-pixel samples of a supersampled pixel are taken.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Sampling {
+    /// At the centers of a regular grid of cells.
+    Grid,
+    /// At a random spot within each grid cell, which trades the regular
+    /// aliasing patterns of the grid for fine noise. The spots are derived from
+    /// the pixel position, so renders are reproducible.
+    Jitter,
+}
+
+impl FromStr for Sampling {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "grid" => Ok(Sampling::Grid),
+            "jitter" => Ok(Sampling::Jitter),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Sampling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Sampling::Grid => "grid",
+            Sampling::Jitter => "jitter",
+        })
+    }
+}
+
+/// Floating point precision of the escape-time math.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Precision {
+    /// `f32`: fastest, good to a zoom of about 1e4.
+    Single,
+    /// `f64`: good to a zoom of about 1e12.
+    Double,
+    /// Perturbation around a high-precision reference orbit through the
+    /// center, see [`crate::deep`]; for zooms beyond what `f64` can resolve.
+    Deep,
+}
+
+impl FromStr for Precision {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "single" => Ok(Precision::Single),
+            "double" => Ok(Precision::Double),
+            "deep" => Ok(Precision::Deep),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Precision::Single => "single",
+            Precision::Double => "double",
+            Precision::Deep => "deep",
+        })
+    }
+}
+
+/// Region of the complex plane shown in an image, shared by the generators
+/// that plot the complex plane.
+///
+/// The real axis points right and the imaginary axis up. The original
+/// `fractal` command had the real axis pointing down instead, so its default
+/// image now comes out turned a quarter counterclockwise.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+    /// Point of the complex plane shown in the middle of the image.
+    pub center: Complex<f64>,
+    /// Magnification; at zoom 1 the shorter side of the image spans 3 units.
+    pub zoom: f64,
+}
+
+impl Viewport {
+    /// Reads and validates the `width`, `height`, `center` and `zoom` arguments.
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let viewport = Viewport {
+            width: args.number("width")?,
+            height: args.number("height")?,
+            center: args.number_with("center", parse_complex)?,
+            zoom: args.number("zoom")?,
+        };
+        if viewport.width == 0 {
+            return Err(args.invalid("width", "must be at least 1"));
+        }
+        if viewport.height == 0 {
+            return Err(args.invalid("height", "must be at least 1"));
+        }
+        if !(viewport.zoom > 0.0 && viewport.zoom.is_finite()) {
+            return Err(args.invalid("zoom", "must be a positive number"));
+        }
+        Ok(viewport)
+    }
+
+    /// Size of one pixel in the complex plane.
+    pub fn pixel_size(&self) -> f64 {
+        3.0 / (self.zoom * self.width.min(self.height) as f64)
+    }
+
+    /// Maps an image position (in pixels, `(0.5, 0.5)` being the middle of the
+    /// top-left pixel) to the complex plane, imaginary axis pointing up.
+    pub fn point(&self, x: f64, y: f64) -> Complex<f64> {
+        self.center + self.offset(x, y)
+    }
+
+    /// Like [`Viewport::point`], but relative to the center, which keeps
+    /// the full precision of tiny offsets.
+    pub fn offset(&self, x: f64, y: f64) -> Complex<f64> {
+        let scale = self.pixel_size();
+        Complex::new((x - self.width as f64 / 2.0) * scale, -(y - self.height as f64 / 2.0) * scale)
+    }
+}
+
+/// Everything the `fractal` generator needs to render an image.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FractalParams {
+    pub width: u32,
+    pub height: u32,
+    pub family: Family,
+    /// The constant `c` of the Julia iteration `z = z * z + c`; unused by the
+    /// other families, which take `c` from the pixel.
+    pub c: Complex<f64>,
+    /// Power `n` of the multibrot iteration `z = z^n + c`.
+    pub exponent: u32,
+    /// Iterations after which a point is considered inside the set.
+    pub iterations: u32,
+    /// Point of the complex plane shown in the middle of the image.
+    pub center: Complex<f64>,
+    /// Magnification; at zoom 1 the shorter side of the image spans 3 units.
+    pub zoom: f64,
+    /// Gradient for escape-time coloring; `None` keeps the classic green
+    /// fractal over a red/blue background.
+    pub palette: Option<Palette>,
+    /// Use the continuous (normalized iteration count) escape time instead of
+    /// whole iterations, which removes the banding between iteration levels.
+    pub smooth: bool,
+    /// Worker threads used to render; 0 means one per CPU. The output does not
+    /// depend on it.
+    pub threads: usize,
+    /// Supersampling factor: every pixel averages `samples` x `samples`
+    /// escape-time colors. 1 samples just the pixel center.
+    pub samples: u32,
+    pub sampling: Sampling,
+    pub precision: Precision,
+    /// Real and imaginary part of `center` as written, kept for deep
+    /// precision so the zoom goes into exactly that point rather than the
+    /// nearest `f64`; see [`deep::reference_orbit`].
+    pub center_digits: Option<(String, String)>,
+}
+
+impl Default for FractalParams {
+    fn default() -> Self {
+        FractalParams {
+            width: 800,
+            height: 800,
+            family: Family::Julia,
+            c: Complex::new(-0.4, 0.6),
+            exponent: 2,
+            iterations: 255,
+            center: Complex::new(0.0, 0.0),
+            zoom: 1.0,
+            palette: None,
+            smooth: false,
+            threads: 0,
+            samples: 1,
+            sampling: Sampling::Grid,
+            precision: Precision::Single,
+            center_digits: None,
+        }
+    }
+}
+
+impl Generator for FractalParams {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn generate(&self) -> Result<DynamicImage> {
+        Ok(DynamicImage::ImageRgb8(render(self)))
+    }
+}
+
+impl FractalParams {
+    /// Argument schema shared by the fractal generators.
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::optional("width", "800"),
+        Argument::optional("height", "800"),
+        Argument::optional("family", "julia")
+            .with_hint("julia, mandelbrot, burning-ship, tricorn, multibrot"),
+        Argument::optional("c", "-0.4+0.6i"),
+        Argument::optional("exponent", "2"),
+        Argument::optional("iterations", "255"),
+        Argument::optional("center", "0+0i"),
+        Argument::optional("zoom", "1"),
+        Argument::optional("palette", "classic")
+            .with_hint("classic, grayscale, fire, ocean, forest, rainbow, ultra or #rrggbb:position,..."),
+        Argument::optional("smooth", "false"),
+        Argument::optional("threads", "0").with_hint("0 = one per CPU"),
+        Argument::optional("samples", "1").with_hint("1-4, per side of a pixel"),
+        Argument::optional("sampling", "grid").with_hint("grid, jitter"),
+        Argument::optional("precision", "single").with_hint("single, double, deep"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let viewport = Viewport::from_arguments(args)?;
+        let mut params = FractalParams {
+            width: viewport.width,
+            height: viewport.height,
+            family: args
+                .value("family")
+                .parse()
+                .map_err(|_| args.invalid("family", "must be one of julia, mandelbrot, burning-ship, tricorn, multibrot"))?,
+            c: args.number_with("c", parse_complex)?,
+            exponent: args.number("exponent")?,
+            iterations: args.number("iterations")?,
+            center: viewport.center,
+            zoom: viewport.zoom,
+            palette: match args.value("palette") {
+                "classic" => None,
+                palette => Some(palette.parse().map_err(|_| {
+                    args.invalid("palette", "must be a palette name or stops like #000000:0,#ffffff:1")
+                })?),
+            },
+            smooth: args.value("smooth").parse().map_err(|_| args.invalid("smooth", "must be true or false"))?,
+            threads: args.number("threads")?,
+            samples: args.number("samples")?,
+            sampling: args.value("sampling").parse().map_err(|_| args.invalid("sampling", "must be grid or jitter"))?,
+            precision: args
+                .value("precision")
+                .parse()
+                .map_err(|_| args.invalid("precision", "must be single, double or deep"))?,
+            center_digits: None,
+        };
+        if params.exponent < 2 {
+            return Err(args.invalid("exponent", "must be at least 2"));
+        }
+        if params.iterations == 0 {
+            return Err(args.invalid("iterations", "must be at least 1"));
+        }
+        if !(1..=4).contains(&params.samples) {
+            return Err(args.invalid("samples", "must be between 1 and 4"));
+        }
+        if params.precision == Precision::Deep && params.family == Family::BurningShip {
+            return Err(args.invalid("precision", "deep does not support burning-ship"));
+        }
+        if params.precision == Precision::Deep {
+            params.center_digits =
+                complex_parts(args.value("center")).map(|(re, im)| (re.to_string(), im.to_string()));
+        }
+        Ok(params)
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![
+            self.width.to_string(),
+            self.height.to_string(),
+            self.family.to_string(),
+            format_complex(self.c),
+            self.exponent.to_string(),
+            self.iterations.to_string(),
+            match &self.center_digits {
+                Some((re, im)) => format!("{},{}", re, im),
+                None => format_complex(self.center),
+            },
+            self.zoom.to_string(),
+            self.palette.as_ref().map_or("classic".to_string(), Palette::to_string),
+            self.smooth.to_string(),
+            self.threads.to_string(),
+            self.samples.to_string(),
+            self.sampling.to_string(),
+            self.precision.to_string(),
+        ]
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport { width: self.width, height: self.height, center: self.center, zoom: self.zoom }
+    }
+
+    /// Size of one pixel in the complex plane.
+    pub fn pixel_size(&self) -> f64 {
+        self.viewport().pixel_size()
+    }
+
+    /// Maps an image position to the complex plane, see [`Viewport::point`].
+    pub fn point(&self, x: f64, y: f64) -> Complex<f64> {
+        self.viewport().point(x, y)
+    }
+
+    /// Maps an image position to an offset from the center, see [`Viewport::offset`].
+    pub fn offset(&self, x: f64, y: f64) -> Complex<f64> {
+        self.viewport().offset(x, y)
+    }
+}
+
+/// Number of worker threads to use for a `threads` argument: 0 means one per CPU.
+pub fn thread_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, usize::from),
+        threads => threads,
+    }
+}
+
+/// Parses a complex number written as `a+bi`, `a-bi`, `bi`, `a` or `a,b`.
+pub fn parse_complex(s: &str) -> Option<Complex<f64>> {
+    let (re, im) = complex_parts(s)?;
+    Some(Complex::new(re.parse().ok()?, im.parse().ok()?))
+}
+
+/// Splits a complex number as [`parse_complex`] reads it into the text of its
+/// real and imaginary part, without checking that they are numbers.
+pub fn complex_parts(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim();
+    if let Some((re, im)) = s.split_once(',') {
+        return Some((re.trim(), im.trim()));
+    }
+    let Some(body) = s.strip_suffix('i') else {
+        return Some((s, "0"));
+    };
+    // The imaginary part starts at the last sign that is not a leading sign
+    // or the sign of an exponent.
+    let split = body
+        .char_indices()
+        .rev()
+        .find(|&(i, ch)| i > 0 && (ch == '+' || ch == '-') && !body[..i].ends_with(['e', 'E']))
+        .map(|(i, _)| i);
+    let (re, im) = match split {
+        Some(i) => (&body[..i], &body[i..]),
+        None => ("0", body),
+    };
+    let im = match im {
+        "" | "+" => "1",
+        "-" => "-1",
+        im => im,
+    };
+    Some((re, im))
+}
+
+/// Formats a complex number so that `parse_complex` reads it back exactly.
+pub fn format_complex(c: Complex<f64>) -> String {
+    format!("{}{:+}i", c.re, c.im)
+}
+
+/// Escape-time value of the point `p`: `None` if its orbit stays bounded for
+/// `params.iterations` steps, otherwise the number of steps it took to escape,
+/// with a fractional part when `params.smooth` is set.
+///
+/// The orbit is computed in `f32` or `f64` as `params.precision` asks; deep
+/// precision needs the reference orbit, see [`deep::escape_time`], so it is
+/// treated as double here.
+pub fn escape_time(p: Complex<f64>, params: &FractalParams) -> Option<f64> {
+    let (modulus, n) = match params.precision {
+        Precision::Single => iterate(Complex::new(p.re as f32, p.im as f32), params),
+        Precision::Double | Precision::Deep => iterate(p, params),
+    };
+    finish_escape(modulus, n, params)
+}
+
+/// Runs the orbit of `p` until it escapes or runs out of iterations, returning
+/// the final modulus and the number of steps taken.
+fn iterate<T: Float>(p: Complex<T>, params: &FractalParams) -> (f64, u32) {
+    let cast = |value: f64| T::from(value).unwrap_or_else(T::zero);
+    // Smooth coloring needs a large bailout radius for the fractional part
+    // to be continuous; the classic coloring keeps the usual radius of 2.
+    let bailout = cast(if params.smooth { 256.0 } else { 2.0 });
+    let (mut z, c) = match params.family {
+        Family::Julia => (p, Complex::new(cast(params.c.re), cast(params.c.im))),
+        _ => (Complex::new(T::zero(), T::zero()), p),
+    };
+    let mut n = 0;
+    while n < params.iterations && z.norm() <= bailout {
+        z = match params.family {
+            Family::Julia | Family::Mandelbrot => z * z,
+            Family::BurningShip => {
+                let folded = Complex::new(z.re.abs(), z.im.abs());
+                folded * folded
+            }
+            Family::Tricorn => z.conj() * z.conj(),
+            Family::Multibrot => z.powu(params.exponent),
+        } + c;
+        n += 1;
+    }
+    (z.norm().to_f64().unwrap_or(f64::INFINITY), n)
+}
+
+/// Escape-time value of an orbit that ended at `modulus` after `n` steps.
+pub(crate) fn finish_escape(modulus: f64, n: u32, params: &FractalParams) -> Option<f64> {
+    let bailout = if params.smooth { 256.0 } else { 2.0 };
+    if modulus <= bailout {
+        return None;
+    }
+    if !params.smooth {
+        return Some(n as f64);
+    }
+    let degree = if params.family == Family::Multibrot { params.exponent } else { 2 };
+    Some((n as f64 + 1.0 - modulus.ln().ln() / (degree as f64).ln()).max(0.0))
+}
+
+/// Color of the pixel at `(x, y)` for the escape-time value `value`.
+fn shade(params: &FractalParams, x: u32, y: u32, value: Option<f64>) -> [u8; 3] {
+    let iterations = params.iterations as f64;
+    match &params.palette {
+        None => {
+            // Use red and blue to be a pretty gradient background
+            let red = (240 * x as u64 / params.width as u64) as u8;
+            let blue = (240 * y as u64 / params.height as u64) as u8;
+            // Use green as the fractal foreground
+            let green = value.map_or(255, |value| (value * 255.0 / iterations) as u8);
+            [red, green, blue]
+        }
+        // Points inside the set are black; the escape time is mapped on a log
+        // scale so that the fast-escaping majority doesn't all share one color.
+        Some(palette) => match value {
+            None => [0, 0, 0],
+            Some(value) => palette.sample((1.0 + value).ln() / (1.0 + iterations).ln()),
+        },
+    }
+}
+
+/// Rows handed to a worker at a time. Small enough that the expensive rows
+/// near the set spread over all workers.
+const BAND_ROWS: usize = 8;
+
+// This code was adapted from https://github.com/PistonDevelopers/image
+pub fn render(params: &FractalParams) -> RgbImage {
+    let orbit = (params.precision == Precision::Deep).then(|| deep::reference_orbit(params));
+    render_parallel(params.width, params.height, params.threads, |x, y| {
+        render_pixel(params, orbit.as_deref(), x, y)
+    })
+}
+
+/// Builds an image from a per-pixel color function on `threads` workers (0
+/// means one per CPU).
+///
+/// The image is split into bands of rows that idle workers pick up one at a
+/// time, so no more workers are started than there are bands. The color of a
+/// pixel may only depend on its coordinates, so the result is the same
+/// whatever the number of threads.
+pub fn render_parallel<F>(width: u32, height: u32, threads: usize, pixel: F) -> RgbImage
+where
+    F: Fn(u32, u32) -> [u8; 3] + Sync,
+{
+    let mut imgbuf = RgbImage::new(width, height);
+    if width == 0 || height == 0 {
+        return imgbuf;
+    }
+
+    let band_len = width as usize * 3 * BAND_ROWS;
+    let workers = thread_count(threads).min((height as usize).div_ceil(BAND_ROWS));
+    let bands = Mutex::new(imgbuf.chunks_mut(band_len).enumerate());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let Some((index, band)) = bands.lock().unwrap().next() else {
+                    break;
+                };
+                let first_row = (index * BAND_ROWS) as u32;
+                // Iterate over the coordinates and pixels of the rows
+                for (offset, rgb) in band.chunks_exact_mut(3).enumerate() {
+                    let x = offset as u32 % width;
+                    let y = first_row + offset as u32 / width;
+                    // Actually set the pixel. red, green, and blue are u8 values!
+                    rgb.copy_from_slice(&pixel(x, y));
+                }
+            });
+        }
+    });
+
+    imgbuf
+}
+
+/// Color of one pixel, averaged over its sub-pixel samples. `orbit` is the
+/// reference orbit of deep precision.
+fn render_pixel(params: &FractalParams, orbit: Option<&[Complex<f64>]>, x: u32, y: u32) -> [u8; 3] {
+    let samples = params.samples.max(1);
+    let mut sum = [0u32; 3];
+    for j in 0..samples {
+        for i in 0..samples {
+            let (dx, dy) = match params.sampling {
+                Sampling::Grid => (0.5, 0.5),
+                Sampling::Jitter => (unit_hash(x, y, i, j, 0), unit_hash(x, y, i, j, 1)),
+            };
+            // Here is the fractal math part
+            let offset = params.offset(
+                x as f64 + (i as f64 + dx) / samples as f64,
+                y as f64 + (j as f64 + dy) / samples as f64,
+            );
+            let value = match orbit {
+                Some(orbit) => deep::escape_time(offset, orbit, params),
+                None => escape_time(params.center + offset, params),
+            };
+            let color = shade(params, x, y, value);
+            for (total, channel) in sum.iter_mut().zip(color) {
+                *total += channel as u32;
+            }
+        }
+    }
+    let count = samples * samples;
+    sum.map(|total| ((total + count / 2) / count) as u8)
+}
+
+/// Pseudo-random number in `[0, 1)` determined by its arguments.
+fn unit_hash(x: u32, y: u32, i: u32, j: u32, axis: u32) -> f64 {
+    let position = (x as u64) << 32 | y as u64;
+    let sample = (i as u64) << 40 | (j as u64) << 20 | axis as u64;
+    rng::unit(rng::mix(position ^ sample.wrapping_mul(rng::GOLDEN)))
+}
@@ -0,0 +1,399 @@
+//! Iterated function systems, rendered with the chaos game.
+//!
+//! A system is a set of affine maps `x' = a*x + b*y + e`, `y' = c*x + d*y + f`,
+//! optionally followed by one of the nonlinear variations of fractal flames.
+//! Besides the built-in systems ([`SYSTEM_NAMES`]) maps can be read from a
+//! text file with one map per line:
+//!
+//! ```text
+//! # a     b     c     d     e   f     [weight] [variation]
+//! 0.5     0     0     0.5   0   0     1
+//! 0.5     0     0     0.5   0.5 0     1        swirl
+//! 0.5     0     0     0.5   0.25 0.433
+//! ```
+//!
+//! The weight is the relative chance of picking the map and defaults to the
+//! area the map scales by. Blank lines and lines starting with `#` are ignored.
+
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use image::{DynamicImage, RgbImage};
+use crate::palette::Palette;
+use crate::rng::Random;
+use crate::{Argument, Arguments, Generator, Result};
+
+/// Nonlinear function applied after the affine part of a map, as in fractal
+/// flames.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Variation {
+    Linear,
+    Sinusoidal,
+    Spherical,
+    Swirl,
+    Horseshoe,
+    Polar,
+}
+
+impl Variation {
+    fn apply(self, x: f64, y: f64) -> (f64, f64) {
+        let r2 = x * x + y * y;
+        match self {
+            Variation::Linear => (x, y),
+            Variation::Sinusoidal => (x.sin(), y.sin()),
+            Variation::Spherical => (x / r2, y / r2),
+            Variation::Swirl => {
+                let (sin, cos) = r2.sin_cos();
+                (x * sin - y * cos, x * cos + y * sin)
+            }
+            Variation::Horseshoe => {
+                let r = r2.sqrt();
+                ((x - y) * (x + y) / r, 2.0 * x * y / r)
+            }
+            Variation::Polar => (x.atan2(y) / std::f64::consts::PI, r2.sqrt() - 1.0),
+        }
+    }
+}
+
+impl FromStr for Variation {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Variation::Linear),
+            "sinusoidal" => Ok(Variation::Sinusoidal),
+            "spherical" => Ok(Variation::Spherical),
+            "swirl" => Ok(Variation::Swirl),
+            "horseshoe" => Ok(Variation::Horseshoe),
+            "polar" => Ok(Variation::Polar),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Variation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Variation::Linear => "linear",
+            Variation::Sinusoidal => "sinusoidal",
+            Variation::Spherical => "spherical",
+            Variation::Swirl => "swirl",
+            Variation::Horseshoe => "horseshoe",
+            Variation::Polar => "polar",
+        })
+    }
+}
+
+/// One map of an iterated function system.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AffineMap {
+    /// `[a, b, c, d, e, f]` of `x' = a*x + b*y + e`, `y' = c*x + d*y + f`.
+    pub coefficients: [f64; 6],
+    /// Relative chance of picking this map.
+    pub weight: f64,
+    pub variation: Variation,
+}
+
+impl AffineMap {
+    pub const fn new(coefficients: [f64; 6], weight: f64) -> Self {
+        AffineMap { coefficients, weight, variation: Variation::Linear }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.coefficients;
+        self.variation.apply(a * x + b * y + e, c * x + d * y + f)
+    }
+}
+
+/// Names accepted by [`named_system`].
+pub const SYSTEM_NAMES: &[&str] = &["fern", "sierpinski", "dragon"];
+
+/// One of the built-in systems, see [`SYSTEM_NAMES`].
+pub fn named_system(name: &str) -> Option<Vec<AffineMap>> {
+    let maps: &[AffineMap] = match name {
+        "fern" => &[
+            AffineMap::new([0.0, 0.0, 0.0, 0.16, 0.0, 0.0], 0.01),
+            AffineMap::new([0.85, 0.04, -0.04, 0.85, 0.0, 1.6], 0.85),
+            AffineMap::new([0.2, -0.26, 0.23, 0.22, 0.0, 1.6], 0.07),
+            AffineMap::new([-0.15, 0.28, 0.26, 0.24, 0.0, 0.44], 0.07),
+        ],
+        "sierpinski" => &[
+            AffineMap::new([0.5, 0.0, 0.0, 0.5, 0.0, 0.0], 1.0),
+            AffineMap::new([0.5, 0.0, 0.0, 0.5, 0.5, 0.0], 1.0),
+            AffineMap::new([0.5, 0.0, 0.0, 0.5, 0.25, 0.433], 1.0),
+        ],
+        "dragon" => &[
+            AffineMap::new([0.5, -0.5, 0.5, 0.5, 0.0, 0.0], 1.0),
+            AffineMap::new([-0.5, -0.5, 0.5, -0.5, 1.0, 0.0], 1.0),
+        ],
+        _ => return None,
+    };
+    Some(maps.to_vec())
+}
+
+/// Parses maps in the file format described in the module documentation.
+/// Errors name the 1-based line.
+pub fn parse_maps(text: &str) -> std::result::Result<Vec<AffineMap>, String> {
+    let mut maps = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |reason: &str| format!("line {}: {}", index + 1, reason);
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if !(6..=8).contains(&words.len()) {
+            return Err(error("expected a b c d e f [weight] [variation]"));
+        }
+        let mut coefficients = [0.0; 6];
+        for (coefficient, word) in coefficients.iter_mut().zip(&words) {
+            *coefficient = word.parse().map_err(|_| error(&format!("'{}' is not a number", word)))?;
+        }
+        let [a, b, c, d, ..] = coefficients;
+        let mut map = AffineMap::new(coefficients, (a * d - b * c).abs().max(0.01));
+        if let Some(weight) = words.get(6) {
+            map.weight = match weight.parse() {
+                Ok(weight) if weight >= 0.0 => weight,
+                _ => return Err(error(&format!("'{}' is not a valid weight", weight))),
+            };
+        }
+        if let Some(variation) = words.get(7) {
+            map.variation = variation.parse().map_err(|_| {
+                error(&format!("unknown variation '{}'", variation))
+            })?;
+        }
+        maps.push(map);
+    }
+    if maps.iter().map(|map| map.weight).sum::<f64>() <= 0.0 {
+        return Err("at least one map with a positive weight is needed".to_string());
+    }
+    Ok(maps)
+}
+
+/// How the points hitting a pixel are turned into a color.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Coloring {
+    /// The palette position follows the log of the hit count.
+    Density,
+    /// As in fractal flames: every map has a palette position, each point
+    /// carries the running average of the maps that led to it, and the log
+    /// density sets the brightness.
+    Flame,
+}
+
+impl FromStr for Coloring {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "density" => Ok(Coloring::Density),
+            "flame" => Ok(Coloring::Flame),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Coloring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Coloring::Density => "density",
+            Coloring::Flame => "flame",
+        })
+    }
+}
+
+/// Most points the chaos game may plot, some minutes of rendering.
+pub const MAX_POINTS: u64 = 10_000_000_000;
+
+/// Everything the `ifs` generator needs to render an image.
+#[derive(Clone, PartialEq, Debug)]
+pub struct IfsParams {
+    pub width: u32,
+    pub height: u32,
+    /// System name or map file, as given.
+    pub system: String,
+    pub maps: Vec<AffineMap>,
+    /// Points plotted by the chaos game.
+    pub points: u64,
+    pub palette: Palette,
+    pub coloring: Coloring,
+    /// Seed of the random map choices; the same seed renders the same image.
+    pub seed: u64,
+}
+
+impl Default for IfsParams {
+    fn default() -> Self {
+        IfsParams {
+            width: 800,
+            height: 800,
+            system: "fern".to_string(),
+            maps: named_system("fern").unwrap_or_default(),
+            points: 1_000_000,
+            palette: Palette::named("forest").expect("forest is a built-in palette"),
+            coloring: Coloring::Density,
+            seed: 0,
+        }
+    }
+}
+
+impl Generator for IfsParams {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn generate(&self) -> Result<DynamicImage> {
+        Ok(DynamicImage::ImageRgb8(render(self)))
+    }
+}
+
+impl IfsParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::optional("width", "800"),
+        Argument::optional("height", "800"),
+        Argument::optional("system", "fern").with_hint("fern, sierpinski, dragon or a file of maps"),
+        Argument::optional("points", "1000000").with_hint("1-10000000000"),
+        Argument::optional("palette", "forest")
+            .with_hint("grayscale, fire, ocean, forest, rainbow, ultra or #rrggbb:position,..."),
+        Argument::optional("coloring", "density").with_hint("density, flame"),
+        Argument::optional("seed", "0"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let system = args.value("system").to_string();
+        let maps = match named_system(&system) {
+            Some(maps) => maps,
+            None => fs::read_to_string(&system)
+                .map_err(|err| err.to_string())
+                .and_then(|text| parse_maps(&text))
+                .map_err(|reason| args.invalid("system", &reason))?,
+        };
+        let params = IfsParams {
+            width: args.number("width")?,
+            height: args.number("height")?,
+            system,
+            maps,
+            points: args.number("points")?,
+            palette: args.value("palette").parse().map_err(|_| {
+                args.invalid("palette", "must be a palette name or stops like #000000:0,#ffffff:1")
+            })?,
+            coloring: args
+                .value("coloring")
+                .parse()
+                .map_err(|_| args.invalid("coloring", "must be density or flame"))?,
+            seed: args.number("seed")?,
+        };
+        if params.width == 0 {
+            return Err(args.invalid("width", "must be at least 1"));
+        }
+        if params.height == 0 {
+            return Err(args.invalid("height", "must be at least 1"));
+        }
+        if !(1..=MAX_POINTS).contains(&params.points) {
+            return Err(args.invalid("points", &format!("must be between 1 and {}", MAX_POINTS)));
+        }
+        Ok(params)
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![
+            self.width.to_string(),
+            self.height.to_string(),
+            self.system.clone(),
+            self.points.to_string(),
+            self.palette.to_string(),
+            self.coloring.to_string(),
+            self.seed.to_string(),
+        ]
+    }
+}
+
+/// Steps taken before plotting, so the point has settled on the attractor.
+const SETTLE_STEPS: usize = 20;
+
+/// Points used to find the extent of the attractor.
+const BOUNDS_POINTS: u64 = 10_000;
+
+/// Plays the chaos game for `points` points, handing every point and its
+/// color (a palette position) to `plot`.
+fn chaos_game(params: &IfsParams, points: u64, mut plot: impl FnMut(f64, f64, f64)) {
+    let mut random = Random::new(params.seed);
+    let total: f64 = params.maps.iter().map(|map| map.weight).sum();
+    let shade = |index: usize| index as f64 / (params.maps.len() - 1).max(1) as f64;
+    let (mut x, mut y, mut color) = (random.next_f64(), random.next_f64(), 0.5);
+    for step in 0..SETTLE_STEPS as u64 + points {
+        let mut pick = random.next_f64() * total;
+        let index = params
+            .maps
+            .iter()
+            .position(|map| {
+                pick -= map.weight;
+                pick < 0.0
+            })
+            .unwrap_or(params.maps.len() - 1);
+        (x, y) = params.maps[index].apply(x, y);
+        color = (color + shade(index)) / 2.0;
+        if !(x.is_finite() && y.is_finite()) {
+            // Variations can blow up at the origin; start over elsewhere.
+            (x, y) = (random.next_f64(), random.next_f64());
+            continue;
+        }
+        if step >= SETTLE_STEPS as u64 {
+            plot(x, y, color);
+        }
+    }
+}
+
+/// Range holding all but the outermost thousandth of `values` at either end.
+fn extent(values: &mut [f64]) -> (f64, f64) {
+    values.sort_by(f64::total_cmp);
+    let last = values.len() - 1;
+    (values[last / 1000], values[last - last / 1000])
+}
+
+/// Renders the attractor, fitted to the image with a small margin and the
+/// y axis pointing up.
+pub fn render(params: &IfsParams) -> RgbImage {
+    let (width, height) = (params.width as usize, params.height as usize);
+    let mut xs = Vec::new();
+    let mut ys = Vec::new();
+    chaos_game(params, params.points.min(BOUNDS_POINTS), |x, y, _| {
+        xs.push(x);
+        ys.push(y);
+    });
+    if xs.is_empty() {
+        return RgbImage::from_pixel(params.width, params.height, image::Rgb(params.palette.sample(0.0)));
+    }
+    let (left, right) = extent(&mut xs);
+    let (bottom, top) = extent(&mut ys);
+    let scale = (width as f64 / (right - left).max(1e-9)).min(height as f64 / (top - bottom).max(1e-9)) / 1.1;
+    let (center_x, center_y) = ((left + right) / 2.0, (bottom + top) / 2.0);
+
+    let mut hits = vec![0u64; width * height];
+    let mut colors = vec![0.0; width * height];
+    chaos_game(params, params.points, |x, y, color| {
+        let column = (x - center_x) * scale + width as f64 / 2.0;
+        let row = height as f64 / 2.0 - (y - center_y) * scale;
+        if (0.0..width as f64).contains(&column) && (0.0..height as f64).contains(&row) {
+            let index = row as usize * width + column as usize;
+            hits[index] += 1;
+            colors[index] += color;
+        }
+    });
+
+    let max = (*hits.iter().max().unwrap_or(&0)).max(1) as f64;
+    let mut imgbuf = RgbImage::new(params.width, params.height);
+    for ((pixel, &count), &color) in imgbuf.pixels_mut().zip(&hits).zip(&colors) {
+        let density = (1.0 + count as f64).ln() / (1.0 + max).ln();
+        pixel.0 = match params.coloring {
+            Coloring::Density => params.palette.sample(density),
+            Coloring::Flame if count == 0 => [0, 0, 0],
+            Coloring::Flame => {
+                // Gamma correction brings out the sparse parts, as flames do.
+                let brightness = density.powf(1.0 / 2.2);
+                params.palette.sample(color / count as f64).map(|channel| (channel as f64 * brightness) as u8)
+            }
+        };
+    }
+    imgbuf
+}
@@ -1,18 +1,128 @@
+use std::ffi::OsString;
+use std::fmt;
 use std::str::FromStr;
 use clap::{value_parser, CommandFactory, Parser, ValueHint};
 use image::DynamicImage;
+use animation::ZoomParams;
+use blur::Kernel;
+use crop::CropParams;
+use flip::Axis;
+use domain::DomainParams;
+use fractal::FractalParams;
+use ifs::IfsParams;
+use newton::NewtonParams;
+use noise::NoiseParams;
+use pattern::PatternParams;
+use resize::{ResizeParams, Size};
+use rotate::RotateParams;
+use trim::TrimParams;
+
+pub mod animation;
+pub mod app;
+pub mod batch;
+pub mod blur;
+pub mod crop;
+pub mod deep;
+pub mod domain;
+pub mod error;
+pub mod expression;
+pub mod flip;
+pub mod fractal;
+pub mod ifs;
+pub mod newton;
+pub mod noise;
+pub mod operation;
+pub mod palette;
+pub mod pattern;
+pub mod pipeline;
+pub mod resize;
+pub mod rng;
+pub mod rotate;
+pub mod script;
+pub mod trim;
+
+pub use error::{MirageError, Result};
+pub use operation::{Argument, Arguments, CustomOperation, Generator, Operation, Registry};
 
 #[derive(Parser)]
 #[command(version)]
 pub struct Cli {
-    #[arg(value_hint = ValueHint::FilePath)]
+    /// Output file, or output directory in batch mode
+    #[arg(value_hint = ValueHint::AnyPath)]
     pub outfile: String,
-    #[arg(value_parser = value_parser!(String))]
+    /// Chain of commands separated by /; values like -5 or -10% are taken as
+    /// arguments rather than flags
+    #[arg(value_parser = value_parser!(String), allow_hyphen_values = true)]
     pub command_vector: Vec<String>,
     #[arg(value_hint = ValueHint::FilePath, required = false, long = "infile")]
     pub infile: Option<String>,
+    /// Pipeline script to run before the commands given on the command line
+    #[arg(value_hint = ValueHint::FilePath, long = "script")]
+    pub script: Option<String>,
+    /// Write the parsed chain to this file in pipeline script format
+    #[arg(value_hint = ValueHint::FilePath, long = "save-script")]
+    pub save_script: Option<String>,
+    /// Run the chain over every image in a directory, matching a glob, or
+    /// given as a file; repeat to add more inputs
+    #[arg(value_hint = ValueHint::AnyPath, long = "batch", conflicts_with = "infile")]
+    pub batch: Vec<String>,
+    /// Output file name template for batch mode, using {name}, {stem} and {ext}
+    #[arg(long = "template", default_value = batch::DEFAULT_TEMPLATE, requires = "batch")]
+    pub template: String,
+    /// Number of files to process in parallel in batch mode [default: number of CPUs]
+    #[arg(long = "jobs", requires = "batch")]
+    pub jobs: Option<usize>,
 }
 
+impl Cli {
+    /// Reorders command line arguments so that options come before OUTFILE.
+    ///
+    /// The chain takes values starting with a hyphen (`-5`, `-10%`), so clap
+    /// would read an option written after it, such as `--infile`, as one more
+    /// word of the chain. Options it knows, and their values, are moved in
+    /// front instead; everything after `--` is left alone.
+    pub fn options_first<I, T>(args: I) -> Vec<OsString>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let mut command = Cli::command();
+        command.build();
+        let mut args = args.into_iter().map(Into::into);
+        let mut options: Vec<OsString> = args.next().into_iter().collect();
+        let mut rest = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                rest.push(arg);
+                rest.extend(args.by_ref());
+                break;
+            }
+            let text = arg.to_str().unwrap_or_default();
+            let (flag, inline_value) = match text.split_once('=') {
+                Some((flag, _)) => (flag, true),
+                None => (text, false),
+            };
+            let option = command.get_arguments().find(|option| match flag.strip_prefix("--") {
+                Some(long) => option.get_long() == Some(long),
+                None => flag.len() == 2 && option.get_short().is_some_and(|short| flag == format!("-{}", short)),
+            });
+            match option {
+                Some(option) => {
+                    let takes_value = option.get_action().takes_values() && !inline_value;
+                    options.push(arg);
+                    if takes_value {
+                        options.extend(args.next());
+                    }
+                }
+                None => rest.push(arg),
+            }
+        }
+        options.extend(rest);
+        options
+    }
+}
+
+/// A clockwise quarter turn, done exactly by moving pixels.
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub enum Rotation {
     Ninety = 90,
@@ -21,277 +131,456 @@ pub enum Rotation {
 }
 
 impl FromStr for Rotation {
-    type Err = &'static str;
+    type Err = MirageError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         match s.parse::<i32>() {
             Ok(90) => Ok(Rotation::Ninety),
             Ok(180) => Ok(Rotation::OneEighty),
             Ok(270) => Ok(Rotation::TwoSeventy),
-            _ => Err("Must be one of 90, 180, 270")
+            _ => Err(MirageError::InvalidRotation(s.to_string()))
         }
     }
 }
 
-#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", *self as i32)
+    }
+}
+
+impl Rotation {
+    /// The quarter turn `degrees` clockwise amounts to, if it is one.
+    pub fn from_degrees(degrees: f64) -> Option<Rotation> {
+        match degrees.rem_euclid(360.0) {
+            90.0 => Some(Rotation::Ninety),
+            180.0 => Some(Rotation::OneEighty),
+            270.0 => Some(Rotation::TwoSeventy),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        // There are 3 rotate functions to choose from (all clockwise):
+        //   .rotate90()
+        //   .rotate180()
+        //   .rotate270()
+        // All three methods return a new image.
+        match self {
+            Rotation::Ninety => img.rotate90(),
+            Rotation::OneEighty => img.rotate180(),
+            Rotation::TwoSeventy => img.rotate270(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum ChainCommands {
-    Blur {},
+    Blur {
+        sigma: f64,
+        kernel: Kernel,
+    },
     Brighten {
         brightness: i32,
     },
     Crop {
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
+        params: CropParams,
     },
     Rotate {
-        rotation: Rotation,
+        params: RotateParams,
     },
     Invert {},
     Grayscale {},
-    Fractal {},
-    Square {
-        red: u8,
-        green: u8,
-        blue: u8,
+    Flip {
+        axis: Axis,
+    },
+    Transpose {},
+    Transverse {},
+    Orient {
+        orientation: u8,
+    },
+    Resize {
+        params: ResizeParams,
+    },
+    Thumbnail {
+        size: Size,
+    },
+    Trim {
+        params: TrimParams,
+    },
+    Fractal {
+        params: FractalParams,
+    },
+    Zoom {
+        params: ZoomParams,
+    },
+    Newton {
+        params: NewtonParams,
+    },
+    Domain {
+        params: DomainParams,
     },
+    Ifs {
+        params: IfsParams,
+    },
+    Pattern {
+        params: PatternParams,
+    },
+    Noise {
+        params: NoiseParams,
+    },
+    Custom(CustomOperation),
 }
 
-pub fn split_command_vector(commands: &Vec<String>) -> Vec<ChainCommands> {
-    let mut chain_commands = Vec::<ChainCommands>::new();
-    let chain_commands_raw = commands
-        .split(|elem| "/".eq(elem))
-        .filter(|elem| !elem.is_empty());
-    for command in chain_commands_raw.filter(|elem| !elem.is_empty()) {
-        match command.get(0).unwrap().as_str() {
-            "blur" => {
-                chain_commands.push(ChainCommands::Blur {});
-            },
-            "brighten" => {
-                if command.len() != 2 {
-                    print_specific_usage_and_exit("Brighten", "<brightness>");
+impl ChainCommands {
+    /// Prototypes of the built-in operations, as registered in `Registry::default()`.
+    pub fn builtins() -> Vec<ChainCommands> {
+        vec![
+            ChainCommands::Blur { sigma: 2.0, kernel: Kernel::Gaussian },
+            ChainCommands::Brighten { brightness: 0 },
+            ChainCommands::Crop { params: CropParams::default() },
+            ChainCommands::Rotate { params: RotateParams::default() },
+            ChainCommands::Invert {},
+            ChainCommands::Grayscale {},
+            ChainCommands::Flip { axis: Axis::Horizontal },
+            ChainCommands::Transpose {},
+            ChainCommands::Transverse {},
+            ChainCommands::Orient { orientation: 1 },
+            ChainCommands::Resize { params: ResizeParams::default() },
+            ChainCommands::Thumbnail { size: Size::Box(128, 128) },
+            ChainCommands::Trim { params: TrimParams::default() },
+            ChainCommands::Fractal { params: FractalParams::default() },
+            ChainCommands::Zoom { params: ZoomParams::default() },
+            ChainCommands::Newton { params: NewtonParams::default() },
+            ChainCommands::Domain { params: DomainParams::default() },
+            ChainCommands::Ifs { params: IfsParams::default() },
+            ChainCommands::Pattern { params: PatternParams::default() },
+            ChainCommands::Noise { params: NoiseParams::default() },
+        ]
+    }
+}
+
+impl Operation for ChainCommands {
+    fn name(&self) -> &str {
+        match self {
+            ChainCommands::Blur { .. } => "blur",
+            ChainCommands::Brighten { .. } => "brighten",
+            ChainCommands::Crop { .. } => "crop",
+            ChainCommands::Rotate { .. } => "rotate",
+            ChainCommands::Invert {} => "invert",
+            ChainCommands::Grayscale {} => "grayscale",
+            ChainCommands::Flip { .. } => "flip",
+            ChainCommands::Transpose {} => "transpose",
+            ChainCommands::Transverse {} => "transverse",
+            ChainCommands::Orient { .. } => "orient",
+            ChainCommands::Resize { .. } => "resize",
+            ChainCommands::Thumbnail { .. } => "thumbnail",
+            ChainCommands::Trim { .. } => "trim",
+            ChainCommands::Fractal { .. } => "fractal",
+            ChainCommands::Zoom { .. } => "zoom",
+            ChainCommands::Newton { .. } => "newton",
+            ChainCommands::Domain { .. } => "domain",
+            ChainCommands::Ifs { .. } => "ifs",
+            ChainCommands::Pattern { .. } => "pattern",
+            ChainCommands::Noise { .. } => "noise",
+            ChainCommands::Custom(custom) => custom.0.name(),
+        }
+    }
+
+    fn arguments(&self) -> &[Argument] {
+        const BLUR: &[Argument] = &[
+            Argument::optional("sigma", "2").with_hint("standard deviation in pixels"),
+            Argument::optional("kernel", "gaussian").with_hint("gaussian, box, stack"),
+        ];
+        const BRIGHTEN: &[Argument] = &[Argument::required("brightness")];
+        const FLIP: &[Argument] = &[Argument::required("axis").with_hint("h, v")];
+        const ORIENT: &[Argument] = &[Argument::required("orientation").with_hint("EXIF orientation, 1-8")];
+        const THUMBNAIL: &[Argument] = &[Argument::required("size").with_hint(resize::SIZE_FORMATS)];
+        match self {
+            ChainCommands::Blur { .. } => BLUR,
+            ChainCommands::Brighten { .. } => BRIGHTEN,
+            ChainCommands::Crop { .. } => CropParams::ARGUMENTS,
+            ChainCommands::Rotate { .. } => RotateParams::ARGUMENTS,
+            ChainCommands::Flip { .. } => FLIP,
+            ChainCommands::Orient { .. } => ORIENT,
+            ChainCommands::Resize { .. } => ResizeParams::ARGUMENTS,
+            ChainCommands::Thumbnail { .. } => THUMBNAIL,
+            ChainCommands::Trim { .. } => TrimParams::ARGUMENTS,
+            ChainCommands::Fractal { .. } => FractalParams::ARGUMENTS,
+            ChainCommands::Zoom { .. } => &ZoomParams::ARGUMENTS,
+            ChainCommands::Newton { .. } => NewtonParams::ARGUMENTS,
+            ChainCommands::Domain { .. } => DomainParams::ARGUMENTS,
+            ChainCommands::Ifs { .. } => IfsParams::ARGUMENTS,
+            ChainCommands::Pattern { .. } => PatternParams::ARGUMENTS,
+            ChainCommands::Noise { .. } => NoiseParams::ARGUMENTS,
+            ChainCommands::Custom(custom) => custom.0.arguments(),
+            _ => &[],
+        }
+    }
+
+    fn usage(&self) -> String {
+        match self {
+            ChainCommands::Custom(custom) => custom.0.usage(),
+            _ => self.arguments().iter().map(|arg| arg.usage()).collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    fn parse(&self, args: &Arguments) -> Result<ChainCommands> {
+        Ok(match self {
+            ChainCommands::Blur { .. } => {
+                let sigma: f64 = args.number("sigma")?;
+                if !(sigma >= 0.0 && sigma.is_finite()) {
+                    return Err(args.invalid("sigma", "must be a number of at least 0"));
                 }
-                chain_commands.push(ChainCommands::Brighten {
-                    brightness: command.get(1).unwrap().parse::<i32>()
-                                       .expect("argument must be a number")
-                });
-            },
-            "crop" => {
-                if command.len() != 5 {
-                    print_specific_usage_and_exit("Crop", "<x> <y> <width> <height>");
+                ChainCommands::Blur {
+                    sigma,
+                    kernel: args
+                        .value("kernel")
+                        .parse()
+                        .map_err(|_| args.invalid("kernel", "must be one of gaussian, box, stack"))?,
                 }
-                chain_commands.push(ChainCommands::Crop {
-                    x: command.get(1).unwrap().parse::<u32>()
-                              .expect("argument must be a number"),
-                    y: command.get(2).unwrap().parse::<u32>()
-                              .expect("argument must be a number"),
-                    width: command.get(3).unwrap().parse::<u32>()
-                                  .expect("argument must be a number"),
-                    height: command.get(4).unwrap().parse::<u32>()
-                                   .expect("argument must be a number"),
-                });
+            }
+            ChainCommands::Brighten { .. } => ChainCommands::Brighten {
+                brightness: args.number("brightness")?,
+            },
+            ChainCommands::Crop { .. } => ChainCommands::Crop {
+                params: CropParams::from_arguments(args)?,
+            },
+            ChainCommands::Rotate { .. } => ChainCommands::Rotate {
+                params: RotateParams::from_arguments(args)?,
             },
-            "rotate" => {
-                if command.len() != 2 {
-                    print_specific_usage_and_exit("Rotate", "<rotation (90, 180, 270)>");
+            ChainCommands::Invert {} => ChainCommands::Invert {},
+            ChainCommands::Grayscale {} => ChainCommands::Grayscale {},
+            ChainCommands::Flip { .. } => ChainCommands::Flip {
+                axis: args.value("axis").parse().map_err(|_| args.invalid("axis", "must be h or v"))?,
+            },
+            ChainCommands::Transpose {} => ChainCommands::Transpose {},
+            ChainCommands::Transverse {} => ChainCommands::Transverse {},
+            ChainCommands::Orient { .. } => {
+                let orientation = args.number("orientation")?;
+                if !(1..=8).contains(&orientation) {
+                    return Err(args.invalid("orientation", "must be between 1 and 8"));
                 }
-                chain_commands.push(ChainCommands::Rotate {
-                    rotation: command.get(1).unwrap().parse::<Rotation>()
-                                     .expect("Invalid value for rotation"),
-                });
+                ChainCommands::Orient { orientation }
+            }
+            ChainCommands::Resize { .. } => ChainCommands::Resize {
+                params: ResizeParams::from_arguments(args)?,
             },
-            "invert" => {
-                chain_commands.push(ChainCommands::Invert {});
+            ChainCommands::Thumbnail { .. } => ChainCommands::Thumbnail {
+                size: resize::parse_size(args)?,
             },
-            "grayscale" => {
-                chain_commands.push(ChainCommands::Grayscale {});
+            ChainCommands::Trim { .. } => ChainCommands::Trim {
+                params: TrimParams::from_arguments(args)?,
             },
-            "fractal" => {
-                chain_commands.push(ChainCommands::Fractal {});
+            ChainCommands::Fractal { .. } => ChainCommands::Fractal {
+                params: FractalParams::from_arguments(args)?,
             },
-            "square" => {
-                if command.len() != 4 {
-                    print_specific_usage_and_exit("Square", "<red> <green> <blue>");
-                }
-                chain_commands.push(ChainCommands::Square {
-                    red: command.get(1).unwrap().parse::<u8>()
-                                .expect("argument must be a number"),
-                    green: command.get(2).unwrap().parse::<u8>()
-                                  .expect("argument must be a number"),
-                    blue: command.get(3).unwrap().parse::<u8>()
-                                 .expect("argument must be a number"),
-                });
+            ChainCommands::Zoom { .. } => ChainCommands::Zoom {
+                params: ZoomParams::from_arguments(args)?,
             },
-            _ => {
-                print_usage_and_exit();
-            }
+            ChainCommands::Newton { .. } => ChainCommands::Newton {
+                params: NewtonParams::from_arguments(args)?,
+            },
+            ChainCommands::Domain { .. } => ChainCommands::Domain {
+                params: DomainParams::from_arguments(args)?,
+            },
+            ChainCommands::Ifs { .. } => ChainCommands::Ifs {
+                params: IfsParams::from_arguments(args)?,
+            },
+            ChainCommands::Pattern { .. } => ChainCommands::Pattern {
+                params: PatternParams::from_arguments(args)?,
+            },
+            ChainCommands::Noise { .. } => ChainCommands::Noise {
+                params: NoiseParams::from_arguments(args)?,
+            },
+            ChainCommands::Custom(custom) => return custom.0.parse(args),
+        })
+    }
+
+    fn values(&self) -> Vec<String> {
+        match self {
+            ChainCommands::Blur { sigma, kernel } => vec![sigma.to_string(), kernel.to_string()],
+            ChainCommands::Brighten { brightness } => vec![brightness.to_string()],
+            ChainCommands::Crop { params } => params.values(),
+            ChainCommands::Rotate { params } => params.values(),
+            ChainCommands::Flip { axis } => vec![axis.to_string()],
+            ChainCommands::Orient { orientation } => vec![orientation.to_string()],
+            ChainCommands::Resize { params } => params.values(),
+            ChainCommands::Thumbnail { size } => vec![size.to_string()],
+            ChainCommands::Trim { params } => params.values(),
+            ChainCommands::Fractal { params } => params.values(),
+            ChainCommands::Zoom { params } => params.values(),
+            ChainCommands::Newton { params } => params.values(),
+            ChainCommands::Domain { params } => params.values(),
+            ChainCommands::Ifs { params } => params.values(),
+            ChainCommands::Pattern { params } => params.values(),
+            ChainCommands::Noise { params } => params.values(),
+            ChainCommands::Custom(custom) => custom.0.values(),
+            _ => Vec::new(),
         }
     }
-    chain_commands
+
+    fn generator(&self) -> Option<&dyn Generator> {
+        match self {
+            ChainCommands::Fractal { params } => Some(params),
+            ChainCommands::Zoom { params } => Some(params),
+            ChainCommands::Newton { params } => Some(params),
+            ChainCommands::Domain { params } => Some(params),
+            ChainCommands::Ifs { params } => Some(params),
+            ChainCommands::Pattern { params } => Some(params),
+            ChainCommands::Noise { params } => Some(params),
+            ChainCommands::Custom(custom) => custom.0.generator(),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, img: &DynamicImage) -> Result<DynamicImage> {
+        if let Some(generator) = self.generator() {
+            return generator.generate();
+        }
+        Ok(match *self {
+            ChainCommands::Blur { sigma, kernel } => blur_image(img, sigma, kernel),
+            ChainCommands::Brighten { brightness } => brighten_image(img, brightness),
+            ChainCommands::Crop { ref params } => crop_image(img, params)?,
+            ChainCommands::Rotate { ref params } => rotate_image(img, params),
+            ChainCommands::Invert {} => invert_image(img),
+            ChainCommands::Grayscale {} => grayscale_image(img),
+            ChainCommands::Flip { axis } => flip::flip(img, axis),
+            ChainCommands::Transpose {} => flip::transpose(img),
+            ChainCommands::Transverse {} => flip::transverse(img),
+            ChainCommands::Orient { orientation } => flip::orient(img, orientation)?,
+            ChainCommands::Resize { ref params } => resize::resize(img, params)?,
+            ChainCommands::Thumbnail { size } => resize::thumbnail(img, size)?,
+            ChainCommands::Trim { ref params } => trim_image(img, params)?,
+            ChainCommands::Custom(ref custom) => return custom.0.apply(img),
+            _ => unreachable!("generators are handled above"),
+        })
+    }
+}
+
+/// Parses a `/`-separated chain using the built-in operations.
+pub fn split_command_vector(commands: &[String]) -> Result<Vec<ChainCommands>> {
+    Registry::default().parse(commands)
 }
 
-fn print_specific_usage_and_exit(command: &str, message: &str) {
-    let mut cmd = Cli::command();
-    cmd.print_help().expect("Should print help list");
-    panic!("{} {}", command, message);
+/// Opens and decodes an image file.
+pub fn open_image(path: &str) -> Result<DynamicImage> {
+    image::open(path).map_err(MirageError::from_decode)
 }
 
-fn print_usage_and_exit() {
-    let mut cmd = Cli::command();
-    cmd.print_help().expect("Should print help list");
-    panic!();
+/// Encodes an image to a file, picking the format from its extension.
+pub fn save_image(img: &DynamicImage, path: &str) -> Result<()> {
+    img.save(path).map_err(MirageError::from_encode)
 }
 
-pub fn blur(infile: String, outfile: String) {
+pub fn blur(infile: String, outfile: String, sigma: f64, kernel: Kernel) -> Result<()> {
     // Here's how you open an existing image file
-    let img = image::open(infile).expect("Failed to open INFILE.");
-    // **OPTION**
-    // Parse the blur amount (an f32) from the command-line and pass it through
-    // to this function, instead of hard-coding it to 2.0.
-    let img2 = img.blur(2.0);
+    let img = open_image(&infile)?;
     // Here's how you save an image to a file.
-    img2.save(outfile).expect("Failed writing OUTFILE.");
+    save_image(&blur_image(&img, sigma, kernel), &outfile)
 }
 
-pub fn brighten(infile: String, outfile: String, brightness: i32) {
+pub fn blur_image(img: &DynamicImage, sigma: f64, kernel: Kernel) -> DynamicImage {
+    // The blur amount is the standard deviation of the kernel in pixels; see
+    // the blur module for how each kernel is applied.
+    blur::blur(img, sigma, kernel)
+}
+
+pub fn brighten(infile: String, outfile: String, brightness: i32) -> Result<()> {
     // See blur() for an example of how to open / save an image.
-    let img = image::open(infile).expect("Failed to open INFILE.");
+    let img = open_image(&infile)?;
+    save_image(&brighten_image(&img, brightness), &outfile)
+}
+
+pub fn brighten_image(img: &DynamicImage, brightness: i32) -> DynamicImage {
     // .brighten() takes one argument, an i32.  Positive numbers brighten the
     // image. Negative numbers darken it.  It returns a new image.
-    let img2 = img.brighten(brightness);
-
-    // Challenge: parse the brightness amount from the command-line and pass it
-    // through to this function.
-    img2.save(outfile).expect("Failed writing OUTFILE.");
+    img.brighten(brightness)
 }
 
-pub fn crop(infile: String, outfile: String, x: u32, y: u32, width: u32, height: u32) {
+pub fn crop(infile: String, outfile: String, params: &CropParams) -> Result<()> {
     // See blur() for an example of how to open an image.
-    let mut img = image::open(infile).expect("Failed to open INFILE.");
-    // .crop() takes four arguments: x: u32, y: u32, width: u32, height: u32
-    // You may hard-code them, if you like.  It returns a new image.
-    let img2 = img.crop(x, y, width, height);
-    // Challenge: parse the four values from the command-line and pass them
-    // through to this function.
-
+    let img = open_image(&infile)?;
     // See blur() for an example of how to save the image.
-    img2.save(outfile).expect("Failed writing OUTFILE.");
+    save_image(&crop_image(&img, params)?, &outfile)
 }
 
-pub fn rotate(infile: String, outfile: String, rotation: Rotation) {
-    // See blur() for an example of how to open an image.
-    let img = image::open(infile).expect("Failed to open INFILE.");
-    // There are 3 rotate functions to choose from (all clockwise):
-    //   .rotate90()
-    //   .rotate180()
-    //   .rotate270()
-    // All three methods return a new image.  Pick one and use it!
-    let img2: DynamicImage;
-    match rotation {
-        Rotation::Ninety => {
-            img2 = img.rotate90();
-        },
-        Rotation::OneEighty => {
-            img2 = img.rotate180();
-        },
-        Rotation::TwoSeventy => {
-            img2 = img.rotate270();
-        }
-    }
-    // Challenge: parse the rotation amount from the command-line, pass it
-    // through to this function to select which method to call.
-
-    // See blur() for an example of how to save the image.
-    img2.save(outfile).expect("Failed writing OUTFILE.");
+pub fn crop_image(img: &DynamicImage, params: &CropParams) -> Result<DynamicImage> {
+    // .crop_imm() takes four arguments: x: u32, y: u32, width: u32, height: u32
+    // and returns a new image, leaving the original untouched. It shrinks a
+    // rectangle that doesn't fit, so the crop module checks it first.
+    crop::crop(img, params)
 }
 
-pub fn invert(infile: String, outfile: String) {
+pub fn rotate(infile: String, outfile: String, params: &RotateParams) -> Result<()> {
     // See blur() for an example of how to open an image.
-    let mut img = image::open(infile).expect("Failed to open INFILE.");
-    // .invert() takes no arguments and converts the image in-place, so you
-    // will use the same image to save out to a different file.
-    img.invert();
-
+    let img = open_image(&infile)?;
     // See blur() for an example of how to save the image.
-    img.save(outfile).expect("Failed writing OUTFILE.");
+    save_image(&rotate_image(&img, params), &outfile)
 }
 
-pub fn grayscale(infile: String, outfile: String) {
-    // See blur() for an example of how to open an image.
-    let img = image::open(infile).expect("Failed to open INFILE.");
-    // .grayscale() takes no arguments. It returns a new image.
-    let img2 = img.grayscale();
+pub fn rotate_image(img: &DynamicImage, params: &RotateParams) -> DynamicImage {
+    // Quarter turns go through Rotation, which moves pixels exactly; the
+    // rotate module interpolates every other angle.
+    rotate::rotate(img, params)
+}
 
+pub fn invert(infile: String, outfile: String) -> Result<()> {
+    // See blur() for an example of how to open an image.
+    let img = open_image(&infile)?;
     // See blur() for an example of how to save the image.
-    img2.save(outfile).expect("Failed writing OUTFILE.");
+    save_image(&invert_image(&img), &outfile)
 }
 
-pub fn generate(outfile: String, red: u8, green: u8, blue: u8) {
-    // Create an ImageBuffer -- see fractal() for an example
-    let square_size = 100;
+pub fn invert_image(img: &DynamicImage) -> DynamicImage {
+    // .invert() takes no arguments and converts the image in-place, so we
+    // invert a copy to leave the caller's image untouched.
+    let mut img2 = img.clone();
+    img2.invert();
+    img2
+}
 
-    let mut imgbuf = image::ImageBuffer::new(square_size, square_size);
-    // Iterate over the coordinates and pixels of the image -- see fractal() for an example
+pub fn grayscale(infile: String, outfile: String) -> Result<()> {
+    // See blur() for an example of how to open an image.
+    let img = open_image(&infile)?;
+    // See blur() for an example of how to save the image.
+    save_image(&grayscale_image(&img), &outfile)
+}
 
-    // Set the image to some solid color. -- see fractal() for an example
+pub fn grayscale_image(img: &DynamicImage) -> DynamicImage {
+    // .grayscale() takes no arguments. It returns a new image.
+    img.grayscale()
+}
 
-    // Challenge: parse some color data from the command-line, pass it through
-    // to this function to use for the solid color.
+pub fn trim(infile: String, outfile: String, params: &TrimParams) -> Result<()> {
+    // See blur() for an example of how to open an image.
+    let img = open_image(&infile)?;
+    // See blur() for an example of how to save the image.
+    save_image(&trim_image(&img, params)?, &outfile)
+}
 
-    // Challenge 2: Generate something more interesting!
-    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
-        let mut new_green = green;
-        let mut new_blue = blue;
-        if x == 0 || x == square_size - 1 {
-            println!("x {} y {}", x, y);
-            new_green = 255 - new_green;
-        } else if y == 0 || y == square_size - 1 {
-            println!("x {} y {}", x, y);
-            new_green = 255 - new_green;
-        }
-        if x as i32 - y as i32 == 0 || x + y == square_size - 1 {
-            new_blue = 255 - new_blue;
-        }
-        *pixel = image::Rgb([red, new_green, new_blue]);
+/// Crops `img` to what `trim::rectangle` keeps, or leaves it as it is when
+/// the whole image is border.
+pub fn trim_image(img: &DynamicImage, params: &TrimParams) -> Result<DynamicImage> {
+    match trim::rectangle(img, params) {
+        Some(rectangle) => crop::crop(img, &rectangle),
+        None => Ok(img.clone()),
     }
+}
 
+pub fn generate(outfile: String, params: &PatternParams) -> Result<()> {
     // See blur() for an example of how to save the image
-    imgbuf.save(outfile).unwrap();
+    save_image(&generate_image(params), &outfile)
 }
 
-// This code was adapted from https://github.com/PistonDevelopers/image
-pub fn fractal(outfile: String) {
-    let width = 800;
-    let height = 800;
-
-    let mut imgbuf = image::ImageBuffer::new(width, height);
-
-    let scale_x = 3.0 / width as f32;
-    let scale_y = 3.0 / height as f32;
-
-    // Iterate over the coordinates and pixels of the image
-    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
-        // Use red and blue to be a pretty gradient background
-        let red = (0.3 * x as f32) as u8;
-        let blue = (0.3 * y as f32) as u8;
-
-        // Use green as the fractal foreground (here is the fractal math part)
-        let cx = y as f32 * scale_x - 1.5;
-        let cy = x as f32 * scale_y - 1.5;
-
-        let c = num_complex::Complex::new(-0.4, 0.6);
-        let mut z = num_complex::Complex::new(cx, cy);
-
-        let mut green = 0;
-        while green < 255 && z.norm() <= 2.0 {
-            z = z * z + c;
-            green += 1;
-        }
+pub fn generate_image(params: &PatternParams) -> DynamicImage {
+    DynamicImage::ImageRgb8(pattern::render(params))
+}
 
-        // Actually set the pixel. red, green, and blue are u8 values!
-        *pixel = image::Rgb([red, green, blue]);
-    }
+pub fn fractal(outfile: String, params: &FractalParams) -> Result<()> {
+    save_image(&fractal_image(params), &outfile)
+}
 
-    imgbuf.save(outfile).unwrap();
-}
\ No newline at end of file
+pub fn fractal_image(params: &FractalParams) -> DynamicImage {
+    DynamicImage::ImageRgb8(fractal::render(params))
+}
@@ -25,10 +25,7 @@
 //
 //     let positive_number: u32 = some_string.parse().expect("Failed to parse a number");
 
-use clap::Parser;
-use std::path::Path;
-use mirage::{blur, brighten, crop, fractal, generate, grayscale, invert, rotate,
-             split_command_vector, ChainCommands, Cli};
+use mirage::{app, Registry};
 
 fn main() {
     // 1. First, you need to implement some basic command-line argument handling
@@ -37,68 +34,10 @@ fn main() {
     //
     // Challenge: If you're feeling really ambitious, you could delete this code
     // and use the "clap" library instead: https://docs.rs/clap/2.32.0/clap/
-    let cli = Cli::parse();
-    println!("{:?}", cli.command_vector);
-    let chain_commands = split_command_vector(&cli.command_vector);
-    for (index, &command) in chain_commands.iter().enumerate() {
-        let infile: Option<String> = if Path::new(&cli.outfile).exists() && index != 0 {
-            Some(cli.outfile.clone())
-        } else {
-            cli.infile.clone()
-        };
-        let outfile = cli.outfile.clone();
-        match command {
-            ChainCommands::Blur {} => {
-                if let Some(infile) = infile {
-                    println!("Blur infile {} and outfile {}", infile, outfile);
-                    // **OPTION**
-                    // Improve the blur implementation -- see the blur() function below
-                    blur(infile, outfile);
-                }
-            }
-            ChainCommands::Brighten { brightness } => {
-                if let Some(infile) = infile {
-                    println!("Brighten infile {} and outfile {}", infile, outfile);
-                    brighten(infile, outfile, brightness);
-                }
-            }
-            ChainCommands::Crop { x, y, width, height } => {
-                if let Some(infile) = infile {
-                    println!("Crop infile {} and outfile {}", infile, outfile);
-                    crop(infile, outfile, x, y, width, height);
-                }
-            }
-            ChainCommands::Rotate { rotation } => {
-                if let Some(infile) = infile {
-                    println!("Rotate infile {} and outfile {}", infile, outfile);
-                    rotate(infile, outfile, rotation);
-                }
-            }
-            ChainCommands::Invert {} => {
-                if let Some(infile) = infile {
-                    println!("Invert infile {} and outfile {}", infile, outfile);
-                    invert(infile, outfile);
-                }
-            }
-            ChainCommands::Grayscale {} => {
-                if let Some(infile) = infile {
-                    println!("Grayscale infile {} and outfile {}", infile, outfile);
-                    grayscale(infile, outfile);
-                }
-            }
-            ChainCommands::Fractal {} => {
-                fractal(outfile);
-            }
-            ChainCommands::Square { red, green, blue } => {
-                generate(outfile, red, green, blue);
-            }
-        }
-    }
-    if Path::new(&cli.outfile).exists() {
-        println!("Result was generated at {:?}", Path::new(&cli.outfile).canonicalize().unwrap());
-    } else {
-        println!("No file was generated. Provide infile or generate image first via fractal or square function");
-    }
+    //
+    // The command-line handling lives in mirage::app so that other crates can
+    // run it with extra operations registered.
+    app::main_with(Registry::default());
 }
 
 // **SUPER CHALLENGE FOR LATER** - Let's face it, you don't have time for this during class.
@@ -0,0 +1,223 @@
+use image::{DynamicImage, RgbImage};
+use num_complex::Complex;
+use crate::fractal::{format_complex, parse_complex, render_parallel, Viewport};
+use crate::palette::hsv_to_rgb;
+use crate::{Argument, Arguments, Generator, Result};
+
+/// Distance to a root at which Newton's method counts as converged.
+const TOLERANCE: f64 = 1e-6;
+
+/// A polynomial, as given on the command line.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Polynomial {
+    /// The product of `(z - root)` over all roots.
+    Roots(Vec<Complex<f64>>),
+    /// Coefficients from the highest degree down to the constant term.
+    Coefficients(Vec<Complex<f64>>),
+}
+
+impl Polynomial {
+    /// Coefficients from the highest degree down to the constant term.
+    pub fn coefficients(&self) -> Vec<Complex<f64>> {
+        match self {
+            Polynomial::Coefficients(coefficients) => coefficients.clone(),
+            Polynomial::Roots(roots) => {
+                let mut coefficients = vec![Complex::new(1.0, 0.0)];
+                for root in roots {
+                    // Multiply by (z - root).
+                    coefficients.push(Complex::new(0.0, 0.0));
+                    for i in (1..coefficients.len()).rev() {
+                        let carry = coefficients[i - 1] * root;
+                        coefficients[i] -= carry;
+                    }
+                }
+                coefficients
+            }
+        }
+    }
+
+    /// All complex roots, found with the Durand-Kerner method when only the
+    /// coefficients are known.
+    pub fn roots(&self) -> Vec<Complex<f64>> {
+        match self {
+            Polynomial::Roots(roots) => roots.clone(),
+            Polynomial::Coefficients(coefficients) => durand_kerner(coefficients),
+        }
+    }
+}
+
+/// Value of the polynomial and of its derivative at `z` (Horner's scheme).
+fn evaluate(coefficients: &[Complex<f64>], z: Complex<f64>) -> (Complex<f64>, Complex<f64>) {
+    let mut value = Complex::new(0.0, 0.0);
+    let mut derivative = Complex::new(0.0, 0.0);
+    for &coefficient in coefficients {
+        derivative = derivative * z + value;
+        value = value * z + coefficient;
+    }
+    (value, derivative)
+}
+
+fn durand_kerner(coefficients: &[Complex<f64>]) -> Vec<Complex<f64>> {
+    let leading = coefficients[0];
+    let monic: Vec<Complex<f64>> = coefficients.iter().map(|c| c / leading).collect();
+    let degree = monic.len() - 1;
+    let seed = Complex::new(0.4, 0.9);
+    let mut roots: Vec<Complex<f64>> = (0..degree).map(|k| seed.powu(k as u32)).collect();
+    for _ in 0..500 {
+        for k in 0..degree {
+            let (value, _) = evaluate(&monic, roots[k]);
+            let denominator = (0..degree)
+                .filter(|&j| j != k)
+                .fold(Complex::new(1.0, 0.0), |product, j| product * (roots[k] - roots[j]));
+            if denominator.norm() > 0.0 {
+                roots[k] -= value / denominator;
+            }
+        }
+    }
+    roots
+}
+
+fn parse_list(s: &str) -> Option<Vec<Complex<f64>>> {
+    s.split(',').map(parse_complex).collect()
+}
+
+fn format_list(values: &[Complex<f64>]) -> String {
+    values.iter().map(|&value| format_complex(value)).collect::<Vec<_>>().join(",")
+}
+
+/// Everything the `newton` generator needs to render an image.
+#[derive(Clone, PartialEq, Debug)]
+pub struct NewtonParams {
+    pub viewport: Viewport,
+    pub polynomial: Polynomial,
+    /// Newton steps after which a point counts as not converging.
+    pub iterations: u32,
+    /// Worker threads, 0 means one per CPU.
+    pub threads: usize,
+}
+
+impl Default for NewtonParams {
+    /// Basins of `z^3 - 1`.
+    fn default() -> Self {
+        NewtonParams {
+            viewport: Viewport { width: 800, height: 800, center: Complex::new(0.0, 0.0), zoom: 1.0 },
+            polynomial: Polynomial::Coefficients(
+                [1.0, 0.0, 0.0, -1.0].iter().map(|&c| Complex::new(c, 0.0)).collect(),
+            ),
+            iterations: 50,
+            threads: 0,
+        }
+    }
+}
+
+impl Generator for NewtonParams {
+    fn size(&self) -> (u32, u32) {
+        (self.viewport.width, self.viewport.height)
+    }
+
+    fn generate(&self) -> Result<DynamicImage> {
+        Ok(DynamicImage::ImageRgb8(render(self)))
+    }
+}
+
+impl NewtonParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::optional("width", "800"),
+        Argument::optional("height", "800"),
+        Argument::optional("roots", "").with_hint("comma separated, like 1,-0.5+0.87i"),
+        Argument::optional("coefficients", "").with_hint("highest degree first; z^3-1 if neither is given"),
+        Argument::optional("iterations", "50"),
+        Argument::optional("center", "0+0i"),
+        Argument::optional("zoom", "1"),
+        Argument::optional("threads", "0").with_hint("0 = one per CPU"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let list = |name: &str| args.number_with(name, parse_list);
+        let polynomial = match (args.value("roots"), args.value("coefficients")) {
+            ("", "") => NewtonParams::default().polynomial,
+            (_, "") => Polynomial::Roots(list("roots")?),
+            ("", _) => {
+                let coefficients = list("coefficients")?;
+                // Leading zeros don't change the polynomial but would break
+                // the root finder.
+                let start = coefficients.iter().position(|c| c.norm() > 0.0).unwrap_or(coefficients.len());
+                Polynomial::Coefficients(coefficients[start..].to_vec())
+            }
+            _ => return Err(args.invalid("coefficients", "give either roots or coefficients, not both")),
+        };
+        if polynomial.coefficients().len() < 2 {
+            return Err(args.invalid("coefficients", "the polynomial must have degree 1 or more"));
+        }
+        let params = NewtonParams {
+            viewport: Viewport::from_arguments(args)?,
+            polynomial,
+            iterations: args.number("iterations")?,
+            threads: args.number("threads")?,
+        };
+        if params.iterations == 0 {
+            return Err(args.invalid("iterations", "must be at least 1"));
+        }
+        Ok(params)
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        let (roots, coefficients) = match &self.polynomial {
+            Polynomial::Roots(roots) => (format_list(roots), String::new()),
+            Polynomial::Coefficients(coefficients) => (String::new(), format_list(coefficients)),
+        };
+        vec![
+            self.viewport.width.to_string(),
+            self.viewport.height.to_string(),
+            roots,
+            coefficients,
+            self.iterations.to_string(),
+            format_complex(self.viewport.center),
+            self.viewport.zoom.to_string(),
+            self.threads.to_string(),
+        ]
+    }
+}
+
+/// Runs Newton's method from `z`. Returns the index of the root it converged
+/// to and the number of steps it took, or `None` if it did not converge.
+pub fn basin(
+    z: Complex<f64>,
+    coefficients: &[Complex<f64>],
+    roots: &[Complex<f64>],
+    iterations: u32,
+) -> Option<(usize, u32)> {
+    let mut z = z;
+    for n in 0..iterations {
+        if let Some(root) = roots.iter().position(|root| (z - root).norm() < TOLERANCE) {
+            return Some((root, n));
+        }
+        let (value, derivative) = evaluate(coefficients, z);
+        if derivative.norm() == 0.0 {
+            return None;
+        }
+        z -= value / derivative;
+    }
+    None
+}
+
+/// Renders the basins of attraction: every root gets its own hue, and points
+/// get darker the more steps they need to converge. Points that don't converge
+/// are black.
+pub fn render(params: &NewtonParams) -> RgbImage {
+    let coefficients = params.polynomial.coefficients();
+    let roots = params.polynomial.roots();
+    let viewport = &params.viewport;
+    render_parallel(viewport.width, viewport.height, params.threads, |x, y| {
+        let z = viewport.point(x as f64 + 0.5, y as f64 + 0.5);
+        match basin(z, &coefficients, &roots, params.iterations) {
+            None => [0, 0, 0],
+            Some((root, steps)) => {
+                let hue = 360.0 * root as f64 / roots.len() as f64;
+                let brightness = (1.0 - steps as f64 / params.iterations as f64).powi(2);
+                hsv_to_rgb(hue, 0.75, 0.15 + 0.85 * brightness)
+            }
+        }
+    })
+}
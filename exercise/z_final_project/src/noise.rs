@@ -0,0 +1,272 @@
+//! Procedural noise textures.
+//!
+//! Everything is derived from integer hashes of the lattice coordinates and
+//! the seed, and only uses arithmetic and square roots, which IEEE 754 fixes
+//! exactly. A given seed therefore renders the same bytes on every platform,
+//! which keeps golden-image tests stable.
+
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::fmt;
+use std::str::FromStr;
+use image::{DynamicImage, RgbImage};
+use crate::fractal::render_parallel;
+use crate::palette::Palette;
+use crate::rng;
+use crate::{Argument, Arguments, Generator, Result};
+
+/// Noise functions the `noise` generator can layer.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Kind {
+    /// Gradient noise on a square lattice.
+    Perlin,
+    /// Gradient noise on a triangular lattice, with fewer directional artifacts.
+    Simplex,
+    /// Distance to the nearest of randomly scattered feature points, giving
+    /// cell-like textures.
+    Worley,
+}
+
+impl FromStr for Kind {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "perlin" => Ok(Kind::Perlin),
+            "simplex" => Ok(Kind::Simplex),
+            "worley" => Ok(Kind::Worley),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Perlin => "perlin",
+            Kind::Simplex => "simplex",
+            Kind::Worley => "worley",
+        })
+    }
+}
+
+/// Hash of a lattice point.
+fn hash(x: i64, y: i64, seed: u64) -> u64 {
+    rng::mix((x as u64).wrapping_mul(rng::GOLDEN) ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f) ^ seed)
+}
+
+/// Unit gradient assigned to a lattice point.
+fn gradient(x: i64, y: i64, seed: u64) -> (f64, f64) {
+    const GRADIENTS: [(f64, f64); 8] = [
+        (1.0, 0.0),
+        (-1.0, 0.0),
+        (0.0, 1.0),
+        (0.0, -1.0),
+        (FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        (-FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        (FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+        (-FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+    ];
+    GRADIENTS[(hash(x, y, seed) >> 61) as usize]
+}
+
+/// Perlin noise in `[-1, 1]`, zero at every lattice point.
+pub fn perlin(x: f64, y: f64, seed: u64) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let corner = |dx: i64, dy: i64| {
+        let (gx, gy) = gradient(ix + dx, iy + dy, seed);
+        gx * (fx - dx as f64) + gy * (fy - dy as f64)
+    };
+    let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let (u, v) = (fade(fx), fade(fy));
+    let value = lerp(lerp(corner(0, 0), corner(1, 0), u), lerp(corner(0, 1), corner(1, 1), u), v);
+    // Unit gradients keep 2D Perlin noise within sqrt(1/2).
+    (value / FRAC_1_SQRT_2).clamp(-1.0, 1.0)
+}
+
+/// Simplex noise in `[-1, 1]` (after Stefan Gustavson's reference version).
+pub fn simplex(x: f64, y: f64, seed: u64) -> f64 {
+    let f2 = 0.5 * (3f64.sqrt() - 1.0);
+    let g2 = (3.0 - 3f64.sqrt()) / 6.0;
+    // Skew to find the triangle, then unskew back to offsets from its corners.
+    let s = (x + y) * f2;
+    let (i, j) = ((x + s).floor(), (y + s).floor());
+    let t = (i + j) * g2;
+    let (x0, y0) = (x - (i - t), y - (j - t));
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+    let corners = [
+        (0, 0, x0, y0),
+        (i1, j1, x0 - i1 as f64 + g2, y0 - j1 as f64 + g2),
+        (1, 1, x0 - 1.0 + 2.0 * g2, y0 - 1.0 + 2.0 * g2),
+    ];
+    let (i, j) = (i as i64, j as i64);
+    let sum: f64 = corners
+        .iter()
+        .map(|&(di, dj, dx, dy)| {
+            let falloff = 0.5 - dx * dx - dy * dy;
+            if falloff <= 0.0 {
+                return 0.0;
+            }
+            let (gx, gy) = gradient(i + di, j + dj, seed);
+            let squared = falloff * falloff;
+            squared * squared * (gx * dx + gy * dy)
+        })
+        .sum();
+    (70.0 * sum).clamp(-1.0, 1.0)
+}
+
+/// Worley (cellular) noise in `[0, 1]`: the distance to the nearest feature
+/// point, one of which lies in every lattice cell.
+pub fn worley(x: f64, y: f64, seed: u64) -> f64 {
+    let (cx, cy) = (x.floor() as i64, y.floor() as i64);
+    let mut nearest = f64::INFINITY;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let h = hash(cx + dx, cy + dy, seed);
+            let fx = (cx + dx) as f64 + rng::unit(h);
+            let fy = (cy + dy) as f64 + rng::unit(hash(h as i64, 0, seed));
+            nearest = nearest.min(((fx - x) * (fx - x) + (fy - y) * (fy - y)).sqrt());
+        }
+    }
+    nearest.min(1.0)
+}
+
+/// Everything the `noise` generator needs to render an image.
+#[derive(Clone, PartialEq, Debug)]
+pub struct NoiseParams {
+    pub width: u32,
+    pub height: u32,
+    pub kind: Kind,
+    /// Size of the coarsest features, in pixels.
+    pub scale: f64,
+    /// Layers of noise, each at twice the frequency of the one before.
+    pub octaves: u32,
+    /// Amplitude of each octave relative to the one before.
+    pub persistence: f64,
+    pub seed: u64,
+    /// Maps the noise value, 0 to 1, to a color.
+    pub palette: Palette,
+    /// Worker threads, 0 means one per CPU.
+    pub threads: usize,
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        NoiseParams {
+            width: 256,
+            height: 256,
+            kind: Kind::Perlin,
+            scale: 64.0,
+            octaves: 4,
+            persistence: 0.5,
+            seed: 0,
+            palette: Palette::named("grayscale").expect("grayscale is a built-in palette"),
+            threads: 0,
+        }
+    }
+}
+
+impl Generator for NoiseParams {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn generate(&self) -> Result<DynamicImage> {
+        Ok(DynamicImage::ImageRgb8(render(self)))
+    }
+}
+
+impl NoiseParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::optional("width", "256"),
+        Argument::optional("height", "256"),
+        Argument::optional("kind", "perlin").with_hint("perlin, simplex, worley"),
+        Argument::optional("scale", "64").with_hint("feature size in pixels"),
+        Argument::optional("octaves", "4").with_hint("1-16"),
+        Argument::optional("persistence", "0.5"),
+        Argument::optional("seed", "0"),
+        Argument::optional("palette", "grayscale")
+            .with_hint("grayscale, fire, ocean, forest, rainbow, ultra or #rrggbb:position,..."),
+        Argument::optional("threads", "0").with_hint("0 = one per CPU"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let params = NoiseParams {
+            width: args.number("width")?,
+            height: args.number("height")?,
+            kind: args
+                .value("kind")
+                .parse()
+                .map_err(|_| args.invalid("kind", "must be one of perlin, simplex, worley"))?,
+            scale: args.number("scale")?,
+            octaves: args.number("octaves")?,
+            persistence: args.number("persistence")?,
+            seed: args.number("seed")?,
+            palette: args.value("palette").parse().map_err(|_| {
+                args.invalid("palette", "must be a palette name or stops like #000000:0,#ffffff:1")
+            })?,
+            threads: args.number("threads")?,
+        };
+        if params.width == 0 {
+            return Err(args.invalid("width", "must be at least 1"));
+        }
+        if params.height == 0 {
+            return Err(args.invalid("height", "must be at least 1"));
+        }
+        if !(params.scale > 0.0 && params.scale.is_finite()) {
+            return Err(args.invalid("scale", "must be a positive number"));
+        }
+        if !(1..=16).contains(&params.octaves) {
+            return Err(args.invalid("octaves", "must be between 1 and 16"));
+        }
+        if !(params.persistence > 0.0 && params.persistence.is_finite()) {
+            return Err(args.invalid("persistence", "must be a positive number"));
+        }
+        Ok(params)
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![
+            self.width.to_string(),
+            self.height.to_string(),
+            self.kind.to_string(),
+            self.scale.to_string(),
+            self.octaves.to_string(),
+            self.persistence.to_string(),
+            self.seed.to_string(),
+            self.palette.to_string(),
+            self.threads.to_string(),
+        ]
+    }
+
+    /// Layered noise at the image position `(x, y)`, in `[0, 1]`.
+    pub fn value(&self, x: f64, y: f64) -> f64 {
+        let (mut frequency, mut amplitude) = (1.0 / self.scale, 1.0);
+        let (mut sum, mut total) = (0.0, 0.0);
+        for octave in 0..self.octaves as u64 {
+            // Every octave gets its own lattice so their artifacts don't line up.
+            let seed = self.seed.wrapping_add(octave.wrapping_mul(0x632be59bd9b4e019));
+            let (u, v) = (x * frequency, y * frequency);
+            let sample = match self.kind {
+                Kind::Perlin => (perlin(u, v, seed) + 1.0) / 2.0,
+                Kind::Simplex => (simplex(u, v, seed) + 1.0) / 2.0,
+                Kind::Worley => worley(u, v, seed),
+            };
+            sum += amplitude * sample;
+            total += amplitude;
+            frequency *= 2.0;
+            amplitude *= self.persistence;
+        }
+        sum / total
+    }
+}
+
+/// Renders the noise, sampled at pixel centers.
+pub fn render(params: &NoiseParams) -> RgbImage {
+    render_parallel(params.width, params.height, params.threads, |x, y| {
+        params.palette.sample(params.value(x as f64 + 0.5, y as f64 + 0.5))
+    })
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use image::DynamicImage;
+use crate::{ChainCommands, MirageError, Result};
+
+/// One entry of an operation's argument schema.
+///
+/// Arguments without a default are required. On the command line arguments can
+/// be given positionally, in schema order, or by name as `name=value`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Argument {
+    pub name: &'static str,
+    pub hint: &'static str,
+    pub default: Option<&'static str>,
+}
+
+impl Argument {
+    pub const fn required(name: &'static str) -> Self {
+        Argument { name, hint: "", default: None }
+    }
+
+    pub const fn optional(name: &'static str, default: &'static str) -> Self {
+        Argument { name, hint: "", default: Some(default) }
+    }
+
+    /// Extra text shown next to the name in usage, e.g. the allowed values.
+    pub const fn with_hint(self, hint: &'static str) -> Self {
+        Argument { hint, ..self }
+    }
+
+    /// How the argument is shown in usage text: `<name>` or `[name=default]`.
+    pub fn usage(&self) -> String {
+        let hint = if self.hint.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", self.hint)
+        };
+        match self.default {
+            None => format!("<{}{}>", self.name, hint),
+            Some(default) => format!("[{}={}{}]", self.name, default, hint),
+        }
+    }
+}
+
+/// Joins two schemas at compile time, e.g. to extend the schema of another
+/// operation. `N` must be the combined length.
+pub const fn concat_arguments<const N: usize>(first: &[Argument], second: &[Argument]) -> [Argument; N] {
+    assert!(first.len() + second.len() == N, "N must be the combined length of both schemas");
+    let mut joined = [Argument::required(""); N];
+    let mut i = 0;
+    while i < first.len() {
+        joined[i] = first[i];
+        i += 1;
+    }
+    while i < N {
+        joined[i] = second[i - first.len()];
+        i += 1;
+    }
+    joined
+}
+
+/// Raw arguments of one command, matched against an operation's schema.
+#[derive(Clone, Debug)]
+pub struct Arguments {
+    command: String,
+    values: Vec<(&'static str, String)>,
+}
+
+impl Arguments {
+    /// Matches `raw` (the words after the command keyword) against the schema
+    /// of `operation`, filling in defaults for anything not given.
+    pub fn parse(operation: &dyn Operation, raw: &[String]) -> Result<Self> {
+        let schema = operation.arguments();
+        let wrong_arity = || MirageError::WrongArity {
+            command: operation.name().to_string(),
+            usage: operation.usage(),
+        };
+        let mut given: Vec<Option<String>> = vec![None; schema.len()];
+        let mut next_positional = 0;
+        for word in raw {
+            let keyword = word.split_once('=').and_then(|(key, value)| {
+                schema.iter().position(|arg| arg.name == key).map(|index| (index, value))
+            });
+            match keyword {
+                Some((index, value)) => given[index] = Some(value.to_string()),
+                None => {
+                    while next_positional < schema.len() && given[next_positional].is_some() {
+                        next_positional += 1;
+                    }
+                    if next_positional == schema.len() {
+                        return Err(wrong_arity());
+                    }
+                    given[next_positional] = Some(word.clone());
+                }
+            }
+        }
+        let mut values = Vec::with_capacity(schema.len());
+        for (arg, value) in schema.iter().zip(given) {
+            match value.or_else(|| arg.default.map(String::from)) {
+                Some(value) => values.push((arg.name, value)),
+                None => return Err(wrong_arity()),
+            }
+        }
+        Ok(Arguments { command: operation.name().to_string(), values })
+    }
+
+    /// Keyword of the command the arguments belong to.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The raw text of an argument declared in the schema.
+    pub fn value(&self, name: &str) -> &str {
+        self.values
+            .iter()
+            .find(|(arg, _)| *arg == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_else(|| panic!("{} declares no argument named {}", self.command, name))
+    }
+
+    /// Parses a numeric argument.
+    pub fn number<T: FromStr>(&self, name: &str) -> Result<T> {
+        let value = self.value(name);
+        value.parse::<T>().map_err(|_| MirageError::BadNumber {
+            command: self.command.clone(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Parses an argument with a custom parser, reporting failure as a bad number.
+    pub fn number_with<T>(&self, name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<T> {
+        let value = self.value(name);
+        parse(value).ok_or_else(|| MirageError::BadNumber {
+            command: self.command.clone(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Error for an argument that parsed but is not acceptable.
+    pub fn invalid(&self, name: &str, reason: &str) -> MirageError {
+        MirageError::InvalidArgument {
+            command: self.command.clone(),
+            value: self.value(name).to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// A step of a mirage chain.
+///
+/// Operations are registered as prototypes in a [`Registry`]: the parser looks
+/// one up by [`name`](Operation::name), checks the words that follow against
+/// its [`arguments`](Operation::arguments) and asks it to [`parse`](Operation::parse)
+/// them into a configured command, which the executor then [`apply`](Operation::apply)s.
+pub trait Operation: fmt::Debug + Send + Sync {
+    /// Keyword that selects the operation on the command line.
+    fn name(&self) -> &str;
+
+    /// Argument schema, in positional order.
+    fn arguments(&self) -> &[Argument] {
+        &[]
+    }
+
+    /// Usage text shown after the name in help and error messages.
+    fn usage(&self) -> String {
+        self.arguments().iter().map(Argument::usage).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Builds a configured command from arguments already checked against the schema.
+    fn parse(&self, args: &Arguments) -> Result<ChainCommands>;
+
+    /// Argument values of a configured operation, in schema order, such that
+    /// parsing them again yields the same command. Required, because scripts
+    /// saved with `--save-script` are written from these; operations without
+    /// arguments return an empty `Vec`.
+    fn values(&self) -> Vec<String>;
+
+    /// The operation as a [`Generator`], if it makes a new image instead of
+    /// transforming the current one.
+    fn generator(&self) -> Option<&dyn Generator> {
+        None
+    }
+
+    /// Runs the configured operation.
+    fn apply(&self, img: &DynamicImage) -> Result<DynamicImage>;
+}
+
+/// A source of new images, such as `fractal` or `pattern`.
+///
+/// Generators don't look at the image before them, so they may only start a
+/// chain that has no input image; see [`crate::pipeline::validate`].
+pub trait Generator: fmt::Debug + Send + Sync {
+    /// Width and height of the image [`generate`](Generator::generate) makes,
+    /// checked against a pixel budget before anything is generated.
+    fn size(&self) -> (u32, u32);
+
+    /// How many images of that size it makes; more than one for animations.
+    fn frames(&self) -> u32 {
+        1
+    }
+
+    /// Makes the image from the generator's parameters.
+    fn generate(&self) -> Result<DynamicImage>;
+}
+
+/// An operation registered from outside mirage, carried inside [`ChainCommands`].
+#[derive(Clone, Debug)]
+pub struct CustomOperation(pub Arc<dyn Operation>);
+
+impl CustomOperation {
+    pub fn new(operation: impl Operation + 'static) -> Self {
+        CustomOperation(Arc::new(operation))
+    }
+}
+
+impl PartialEq for CustomOperation {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || format!("{:?}", self.0) == format!("{:?}", other.0)
+    }
+}
+
+/// The set of operations the parser, help output and executor know about.
+#[derive(Clone, Debug)]
+pub struct Registry {
+    operations: Vec<Arc<dyn Operation>>,
+}
+
+impl Registry {
+    /// A registry without any operations, not even the built-in ones.
+    pub fn empty() -> Self {
+        Registry { operations: Vec::new() }
+    }
+
+    /// Adds an operation, replacing any registered under the same name.
+    pub fn register(&mut self, operation: impl Operation + 'static) -> &mut Self {
+        self.operations.retain(|existing| existing.name() != operation.name());
+        self.operations.push(Arc::new(operation));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Operation> {
+        self.operations.iter().find(|op| op.name() == name).map(|op| op.as_ref())
+    }
+
+    pub fn operations(&self) -> impl Iterator<Item = &dyn Operation> {
+        self.operations.iter().map(|op| op.as_ref())
+    }
+
+    /// Parses a `/`-separated chain of commands.
+    pub fn parse(&self, commands: &[String]) -> Result<Vec<ChainCommands>> {
+        commands
+            .split(|elem| "/".eq(elem))
+            .filter(|elem| !elem.is_empty())
+            .map(|command| self.parse_command(command))
+            .collect()
+    }
+
+    /// Parses a single command: its keyword followed by its arguments.
+    pub fn parse_command(&self, command: &[String]) -> Result<ChainCommands> {
+        let operation = self
+            .get(&command[0])
+            .ok_or_else(|| MirageError::UnknownCommand(command[0].clone()))?;
+        let args = Arguments::parse(operation, &command[1..])?;
+        operation.parse(&args)
+    }
+
+    /// One line per operation, for the help output.
+    pub fn help(&self) -> String {
+        let mut help = String::from("Commands (separate chained commands with /):\n");
+        for operation in self.operations() {
+            help.push_str(&format!("  {} {}\n", operation.name(), operation.usage()).replace(" \n", "\n"));
+        }
+        help
+    }
+}
+
+impl Default for Registry {
+    /// A registry with every built-in operation.
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        for operation in ChainCommands::builtins() {
+            registry.register(operation);
+        }
+        registry
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A color gradient defined by stops at positions between 0 and 1.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Palette {
+    stops: Vec<(f64, [u8; 3])>,
+}
+
+/// Names accepted by [`Palette::named`].
+pub const PALETTE_NAMES: &[&str] = &["grayscale", "fire", "ocean", "forest", "rainbow", "ultra"];
+
+impl Palette {
+    /// Builds a palette from stops, which are sorted by position. At least two
+    /// stops are needed and every position must lie in `[0, 1]`.
+    pub fn new(mut stops: Vec<(f64, [u8; 3])>) -> Option<Self> {
+        if stops.len() < 2 || stops.iter().any(|(position, _)| !(0.0..=1.0).contains(position)) {
+            return None;
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Some(Palette { stops })
+    }
+
+    /// One of the built-in palettes, see [`PALETTE_NAMES`].
+    pub fn named(name: &str) -> Option<Self> {
+        let stops: &[(f64, u32)] = match name {
+            "grayscale" => &[(0.0, 0x000000), (1.0, 0xffffff)],
+            "fire" => &[(0.0, 0x000000), (0.35, 0x8b0000), (0.6, 0xff8800), (0.85, 0xffdd00), (1.0, 0xffffff)],
+            "ocean" => &[(0.0, 0x000010), (0.4, 0x003f7f), (0.75, 0x00b4d8), (1.0, 0xe0ffff)],
+            "forest" => &[(0.0, 0x000000), (0.3, 0x0b3d0b), (0.65, 0x2e8b22), (1.0, 0xccff90)],
+            "rainbow" => &[
+                (0.0, 0xff0000),
+                (0.2, 0xffff00),
+                (0.4, 0x00ff00),
+                (0.6, 0x00ffff),
+                (0.8, 0x0000ff),
+                (1.0, 0xff00ff),
+            ],
+            "ultra" => &[
+                (0.0, 0x000764),
+                (0.16, 0x206bcb),
+                (0.42, 0xedffff),
+                (0.6425, 0xffaa00),
+                (0.8575, 0x000200),
+                (1.0, 0x000764),
+            ],
+            _ => return None,
+        };
+        Palette::new(stops.iter().map(|&(position, rgb)| (position, hex_to_rgb(rgb))).collect())
+    }
+
+    /// Color at `t`, linearly interpolated between the surrounding stops.
+    /// Values outside `[0, 1]` are clamped.
+    pub fn sample(&self, t: f64) -> [u8; 3] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let upper = self.stops.iter().position(|(position, _)| *position >= t);
+        match upper {
+            None => self.stops[self.stops.len() - 1].1,
+            Some(0) => self.stops[0].1,
+            Some(i) => {
+                let (start, from) = self.stops[i - 1];
+                let (end, to) = self.stops[i];
+                let local = if end > start { (t - start) / (end - start) } else { 1.0 };
+                let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * local).round() as u8;
+                [mix(from[0], to[0]), mix(from[1], to[1]), mix(from[2], to[2])]
+            }
+        }
+    }
+}
+
+/// Converts hue (degrees), saturation and value (both 0 to 1) to RGB.
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> [u8; 3] {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    [r, g, b].map(|channel| ((channel + m) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+fn hex_to_rgb(rgb: u32) -> [u8; 3] {
+    [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]
+}
+
+/// Formats a color as `#rrggbb`, the form `parse_color` reads.
+pub fn format_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Parses `#rrggbb` (the `#` is optional).
+pub fn parse_color(s: &str) -> Option<[u8; 3]> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().map(hex_to_rgb)
+}
+
+impl FromStr for Palette {
+    type Err = ();
+
+    /// Accepts a palette name or a list of stops like
+    /// `#000000:0,#ff8800:0.5,#ffffff:1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(palette) = Palette::named(s) {
+            return Ok(palette);
+        }
+        let mut stops = Vec::new();
+        for stop in s.split(',') {
+            let (color, position) = stop.split_once(':').ok_or(())?;
+            stops.push((position.parse().map_err(|_| ())?, parse_color(color).ok_or(())?));
+        }
+        Palette::new(stops).ok_or(())
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stops: Vec<String> = self
+            .stops
+            .iter()
+            .map(|&(position, color)| format!("{}:{}", format_color(color), position))
+            .collect();
+        f.write_str(&stops.join(","))
+    }
+}
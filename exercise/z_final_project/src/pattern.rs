@@ -0,0 +1,198 @@
+use std::fmt;
+use std::str::FromStr;
+use image::{DynamicImage, Rgb, RgbImage};
+use crate::palette::{format_color, parse_color};
+use crate::{Argument, Arguments, Generator, Result};
+
+/// What the `pattern` generator draws with its two colors.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Mode {
+    /// The whole image in the first color.
+    Solid,
+    /// A gradient from the first to the second color along `angle`, spanning
+    /// the image from corner to corner.
+    Linear,
+    /// A gradient from the first color in the middle to the second color in
+    /// the corners.
+    Radial,
+    /// Squares of `size` pixels, the top-left one in the first color.
+    Checkerboard,
+    /// Stripes `size` pixels wide running along `angle`.
+    Stripes,
+    /// Lines `line` pixels wide every `size` pixels in the first color, over
+    /// the second color.
+    Grid,
+}
+
+impl FromStr for Mode {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "solid" => Ok(Mode::Solid),
+            "linear" => Ok(Mode::Linear),
+            "radial" => Ok(Mode::Radial),
+            "checkerboard" => Ok(Mode::Checkerboard),
+            "stripes" => Ok(Mode::Stripes),
+            "grid" => Ok(Mode::Grid),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Solid => "solid",
+            Mode::Linear => "linear",
+            Mode::Radial => "radial",
+            Mode::Checkerboard => "checkerboard",
+            Mode::Stripes => "stripes",
+            Mode::Grid => "grid",
+        })
+    }
+}
+
+/// Everything the `pattern` generator needs to draw an image.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PatternParams {
+    pub width: u32,
+    pub height: u32,
+    pub mode: Mode,
+    /// First color: the solid fill, gradient start, top-left cell, first
+    /// stripe or grid lines.
+    pub from: [u8; 3],
+    /// Second color: gradient end, other cells and stripes, grid background.
+    pub to: [u8; 3],
+    /// Cell, stripe or grid spacing in pixels.
+    pub size: u32,
+    /// Direction of linear gradients and stripes in degrees, counterclockwise
+    /// from pointing right.
+    pub angle: f64,
+    /// Width of grid lines in pixels.
+    pub line: u32,
+}
+
+impl Default for PatternParams {
+    fn default() -> Self {
+        PatternParams {
+            width: 256,
+            height: 256,
+            mode: Mode::Solid,
+            from: [255, 255, 255],
+            to: [0, 0, 0],
+            size: 32,
+            angle: 0.0,
+            line: 1,
+        }
+    }
+}
+
+impl Generator for PatternParams {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn generate(&self) -> Result<DynamicImage> {
+        Ok(DynamicImage::ImageRgb8(render(self)))
+    }
+}
+
+impl PatternParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::optional("width", "256"),
+        Argument::optional("height", "256"),
+        Argument::optional("mode", "solid").with_hint("solid, linear, radial, checkerboard, stripes, grid"),
+        Argument::optional("from", "#ffffff"),
+        Argument::optional("to", "#000000"),
+        Argument::optional("size", "32").with_hint("cell, stripe or grid spacing in pixels"),
+        Argument::optional("angle", "0").with_hint("degrees, for linear and stripes"),
+        Argument::optional("line", "1").with_hint("grid line width in pixels"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let color = |name: &str| {
+            args.number_with(name, parse_color).map_err(|_| args.invalid(name, "must be a color like #ff8800"))
+        };
+        let params = PatternParams {
+            width: args.number("width")?,
+            height: args.number("height")?,
+            mode: args
+                .value("mode")
+                .parse()
+                .map_err(|_| args.invalid("mode", "must be one of solid, linear, radial, checkerboard, stripes, grid"))?,
+            from: color("from")?,
+            to: color("to")?,
+            size: args.number("size")?,
+            angle: args.number("angle")?,
+            line: args.number("line")?,
+        };
+        if params.width == 0 {
+            return Err(args.invalid("width", "must be at least 1"));
+        }
+        if params.height == 0 {
+            return Err(args.invalid("height", "must be at least 1"));
+        }
+        if params.size == 0 {
+            return Err(args.invalid("size", "must be at least 1"));
+        }
+        if !params.angle.is_finite() {
+            return Err(args.invalid("angle", "must be a finite number"));
+        }
+        if !(1..=params.size).contains(&params.line) {
+            return Err(args.invalid("line", "must be between 1 and size"));
+        }
+        Ok(params)
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![
+            self.width.to_string(),
+            self.height.to_string(),
+            self.mode.to_string(),
+            format_color(self.from),
+            format_color(self.to),
+            self.size.to_string(),
+            self.angle.to_string(),
+            self.line.to_string(),
+        ]
+    }
+}
+
+/// `from` at `t = 0` to `to` at `t = 1`.
+fn mix(from: [u8; 3], to: [u8; 3], t: f64) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0);
+    [0, 1, 2].map(|i| (from[i] as f64 + (to[i] as f64 - from[i] as f64) * t).round() as u8)
+}
+
+/// Draws the pattern; every pixel is colored from its center.
+pub fn render(params: &PatternParams) -> RgbImage {
+    let (width, height) = (params.width as f64, params.height as f64);
+    let (sin, cos) = params.angle.to_radians().sin_cos();
+    // Image y points down, so the direction's y is flipped to keep the angle
+    // counterclockwise on screen.
+    let along = |x: f64, y: f64| x * cos - y * sin;
+    // Linear gradients run between the corners that lie furthest apart along
+    // the direction.
+    let reach = (width * cos.abs() + height * sin.abs()) / 2.0;
+    let corner = (width * width + height * height).sqrt() / 2.0;
+    let size = params.size;
+    RgbImage::from_fn(params.width, params.height, |x, y| {
+        // Pixel centers, relative to the middle of the image.
+        let (cx, cy) = (x as f64 + 0.5 - width / 2.0, y as f64 + 0.5 - height / 2.0);
+        let first = match params.mode {
+            Mode::Solid => true,
+            Mode::Linear => return Rgb(mix(params.from, params.to, (along(cx, cy) + reach) / (2.0 * reach))),
+            Mode::Radial => return Rgb(mix(params.from, params.to, cx.hypot(cy) / corner)),
+            Mode::Checkerboard => (x / size + y / size).is_multiple_of(2),
+            Mode::Stripes => {
+                // Stripes run along the angle, so they alternate across it.
+                let across = x as f64 * sin + y as f64 * cos;
+                (across / size as f64).floor().rem_euclid(2.0) == 0.0
+            }
+            Mode::Grid => x % size < params.line || y % size < params.line,
+        };
+        Rgb(if first { params.from } else { params.to })
+    })
+}
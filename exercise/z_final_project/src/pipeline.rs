@@ -0,0 +1,73 @@
+use image::DynamicImage;
+use crate::{ChainCommands, MirageError, Operation, Result};
+
+/// The most pixels a step of a chain may make, 4 GB as 8-bit RGBA.
+pub const MAX_PIXELS: u64 = 1 << 30;
+
+/// Checks that generators only appear where they can't throw work away, and
+/// only make as many pixels as the budget allows.
+///
+/// A generator (`fractal`, `pattern`, ...) starts a fresh image, so it may only
+/// be the first command of a chain, and only when there is no input image.
+/// Anywhere else it would silently discard the image before it. Its
+/// [`size`](crate::Generator::size) and [`frames`](crate::Generator::frames)
+/// are known up front, so one making more than [`MAX_PIXELS`] in all fails
+/// here instead of when allocating or after hours of rendering.
+pub fn validate(has_input: bool, commands: &[ChainCommands]) -> Result<()> {
+    for (index, command) in commands.iter().enumerate() {
+        let Some(generator) = command.generator() else {
+            continue;
+        };
+        if has_input || index > 0 {
+            return Err(MirageError::MisplacedGenerator { command: command.name().to_string(), position: index + 1 });
+        }
+        let (width, height) = generator.size();
+        let frames = generator.frames();
+        if width as u64 * height as u64 * frames as u64 > MAX_PIXELS {
+            let value = match frames {
+                1 => format!("{}x{}", width, height),
+                _ => format!("{}x{}x{}", width, height, frames),
+            };
+            return Err(MirageError::InvalidArgument {
+                command: command.name().to_string(),
+                value,
+                reason: format!("would make more than {} pixels", MAX_PIXELS),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Applies a single step of a validated chain to the current image.
+///
+/// Generators make the first image of a chain; transforms need an image to
+/// work on, so they are skipped while there is none.
+fn apply_command(image: Option<DynamicImage>, command: &ChainCommands) -> Result<Option<DynamicImage>> {
+    match command.generator() {
+        Some(generator) => generator.generate().map(Some),
+        None => image.map(|img| command.apply(&img)).transpose(),
+    }
+}
+
+/// Threads an image through every step of the chain in memory, so the input is
+/// decoded once and nothing is encoded until the caller saves the result.
+///
+/// The chain is [`validate`]d first, so a misplaced generator fails before any
+/// work is done.
+pub fn execute(image: Option<DynamicImage>, commands: &[ChainCommands]) -> Result<Option<DynamicImage>> {
+    execute_with(image, commands, |_, _| {})
+}
+
+/// Like [`execute`], showing `inspect` every command together with the image
+/// it is about to be applied to, so callers can report on the steps.
+pub fn execute_with(
+    image: Option<DynamicImage>,
+    commands: &[ChainCommands],
+    mut inspect: impl FnMut(&ChainCommands, Option<&DynamicImage>),
+) -> Result<Option<DynamicImage>> {
+    validate(image.is_some(), commands)?;
+    commands.iter().try_fold(image, |image, command| {
+        inspect(command, image.as_ref());
+        apply_command(image, command)
+    })
+}
@@ -0,0 +1,279 @@
+//! Scaling to exact sizes, single dimensions, percentages and boxes.
+
+use std::fmt;
+use std::str::FromStr;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use crate::pipeline::MAX_PIXELS;
+use crate::{Argument, Arguments, MirageError, Result};
+
+/// The size asked for, as written on the command line.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Size {
+    /// `WxH`: a box, see [`Mode`] for how the image is made to match it.
+    Box(u32, u32),
+    /// `W` or `Wx`: this width, with the height keeping the aspect ratio.
+    Width(u32),
+    /// `xH`: this height, with the width keeping the aspect ratio.
+    Height(u32),
+    /// `P%`: both sides scaled by a percentage.
+    Percent(f64),
+}
+
+impl FromStr for Size {
+    type Err = ();
+
+    /// Sides must be at least 1 and percentages positive.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let side = |s: &str| s.parse::<u32>().ok().filter(|&side| side > 0).ok_or(());
+        if let Some(percent) = s.strip_suffix('%') {
+            let percent: f64 = percent.parse().map_err(|_| ())?;
+            return if percent > 0.0 && percent.is_finite() { Ok(Size::Percent(percent)) } else { Err(()) };
+        }
+        match s.split_once('x') {
+            None => side(s).map(Size::Width),
+            Some((width, "")) => side(width).map(Size::Width),
+            Some(("", height)) => side(height).map(Size::Height),
+            Some((width, height)) => Ok(Size::Box(side(width)?, side(height)?)),
+        }
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Size::Box(width, height) => write!(f, "{}x{}", width, height),
+            Size::Width(width) => write!(f, "{}x", width),
+            Size::Height(height) => write!(f, "x{}", height),
+            Size::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl Size {
+    /// Dimensions for an image of `width` by `height` when the aspect ratio is
+    /// kept, or the box itself; never smaller than 1 by 1, and `None` if a side
+    /// would not fit in a `u32`.
+    pub fn dimensions(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let scaled = |side: u32, factor: f64| {
+            let side = (side as f64 * factor).round().max(1.0);
+            (side <= u32::MAX as f64).then_some(side as u32)
+        };
+        match *self {
+            Size::Box(box_width, box_height) => Some((box_width, box_height)),
+            Size::Width(target) => Some((target, scaled(height, target as f64 / width as f64)?)),
+            Size::Height(target) => Some((scaled(width, target as f64 / height as f64)?, target)),
+            Size::Percent(percent) => Some((scaled(width, percent / 100.0)?, scaled(height, percent / 100.0)?)),
+        }
+    }
+}
+
+/// How an image is made to match a `WxH` box.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Mode {
+    /// Stretches to exactly the box.
+    Exact,
+    /// The largest size that fits in the box with the aspect ratio kept.
+    Fit,
+    /// Exactly the box: scaled to cover it with the aspect ratio kept, then
+    /// cropped to it around the center.
+    Fill,
+    /// The smallest size that covers the box with the aspect ratio kept,
+    /// without cropping.
+    Cover,
+}
+
+impl FromStr for Mode {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Mode::Exact),
+            "fit" => Ok(Mode::Fit),
+            "fill" => Ok(Mode::Fill),
+            "cover" => Ok(Mode::Cover),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Exact => "exact",
+            Mode::Fit => "fit",
+            Mode::Fill => "fill",
+            Mode::Cover => "cover",
+        })
+    }
+}
+
+/// Resampling filters, from fastest to sharpest.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Filter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl FromStr for Filter {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(Filter::Nearest),
+            "triangle" => Ok(Filter::Triangle),
+            "catmull-rom" => Ok(Filter::CatmullRom),
+            "gaussian" => Ok(Filter::Gaussian),
+            "lanczos3" => Ok(Filter::Lanczos3),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Filter::Nearest => "nearest",
+            Filter::Triangle => "triangle",
+            Filter::CatmullRom => "catmull-rom",
+            Filter::Gaussian => "gaussian",
+            Filter::Lanczos3 => "lanczos3",
+        })
+    }
+}
+
+impl From<Filter> for FilterType {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Nearest => FilterType::Nearest,
+            Filter::Triangle => FilterType::Triangle,
+            Filter::CatmullRom => FilterType::CatmullRom,
+            Filter::Gaussian => FilterType::Gaussian,
+            Filter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Everything `resize` needs to scale an image.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ResizeParams {
+    pub size: Size,
+    /// Only used when `size` is a box.
+    pub mode: Mode,
+    pub filter: Filter,
+}
+
+impl Default for ResizeParams {
+    fn default() -> Self {
+        ResizeParams { size: Size::Percent(100.0), mode: Mode::Exact, filter: Filter::Lanczos3 }
+    }
+}
+
+/// How sizes are written, for usage text and errors.
+pub const SIZE_FORMATS: &str = "WxH, Wx, xH or P%";
+
+impl ResizeParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::required("size").with_hint(SIZE_FORMATS),
+        Argument::optional("mode", "exact").with_hint("exact, fit, fill, cover"),
+        Argument::optional("filter", "lanczos3").with_hint("nearest, triangle, catmull-rom, gaussian, lanczos3"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        Ok(ResizeParams {
+            size: parse_size(args)?,
+            mode: args
+                .value("mode")
+                .parse()
+                .map_err(|_| args.invalid("mode", "must be one of exact, fit, fill, cover"))?,
+            filter: args.value("filter").parse().map_err(|_| {
+                args.invalid("filter", "must be one of nearest, triangle, catmull-rom, gaussian, lanczos3")
+            })?,
+        })
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![self.size.to_string(), self.mode.to_string(), self.filter.to_string()]
+    }
+}
+
+/// Parses the `size` argument, shared with `thumbnail`.
+pub fn parse_size(args: &Arguments) -> Result<Size> {
+    args.value("size")
+        .parse()
+        .map_err(|_| args.invalid("size", &format!("must be one of {} with sides of at least 1", SIZE_FORMATS)))
+}
+
+/// `dimensions` if they exist and stay within `MAX_PIXELS`, or an error
+/// blaming `size`.
+fn within_budget(command: &str, size: Size, dimensions: Option<(u32, u32)>) -> Result<(u32, u32)> {
+    dimensions.filter(|&(width, height)| width as u64 * height as u64 <= MAX_PIXELS).ok_or_else(|| {
+        MirageError::InvalidArgument {
+            command: command.to_string(),
+            value: size.to_string(),
+            reason: format!("would make an image of more than {} pixels", MAX_PIXELS),
+        }
+    })
+}
+
+/// Scale factors that make an image of `width` by `height` fit in and cover a
+/// box, in percent.
+fn fit_and_cover(width: u32, height: u32, box_width: u32, box_height: u32) -> (f64, f64) {
+    let (horizontal, vertical) = (box_width as f64 / width as f64, box_height as f64 / height as f64);
+    (horizontal.min(vertical) * 100.0, horizontal.max(vertical) * 100.0)
+}
+
+pub fn resize(img: &DynamicImage, params: &ResizeParams) -> Result<DynamicImage> {
+    let filter = params.filter.into();
+    let (width, height) = (img.width(), img.height());
+    let checked = |dimensions| within_budget("resize", params.size, dimensions);
+    Ok(match (params.size, params.mode) {
+        (Size::Box(box_width, box_height), Mode::Fit) => {
+            let (fit, _) = fit_and_cover(width, height, box_width, box_height);
+            checked(Size::Percent(fit).dimensions(width, height))?;
+            img.resize(box_width, box_height, filter)
+        }
+        (Size::Box(box_width, box_height), mode @ (Mode::Fill | Mode::Cover)) => {
+            let (_, cover) = fit_and_cover(width, height, box_width, box_height);
+            let (cover_width, cover_height) = checked(Size::Percent(cover).dimensions(width, height))?;
+            // Rounding may leave the scaled image a pixel short of the box.
+            let (cover_width, cover_height) = (cover_width.max(box_width), cover_height.max(box_height));
+            let covered = img.resize_exact(cover_width, cover_height, filter);
+            match mode {
+                Mode::Fill => covered.crop_imm(
+                    (cover_width - box_width) / 2,
+                    (cover_height - box_height) / 2,
+                    box_width,
+                    box_height,
+                ),
+                _ => covered,
+            }
+        }
+        (size, _) => {
+            let (new_width, new_height) = checked(size.dimensions(width, height))?;
+            img.resize_exact(new_width, new_height, filter)
+        }
+    })
+}
+
+/// A quick preview: fits in a `WxH` box with the aspect ratio kept, or follows
+/// the other sizes like `resize`. Averages pixels instead of filtering, which
+/// is much faster when shrinking a lot.
+pub fn thumbnail(img: &DynamicImage, size: Size) -> Result<DynamicImage> {
+    let (width, height) = (img.width(), img.height());
+    Ok(match size {
+        Size::Box(box_width, box_height) => {
+            let (fit, _) = fit_and_cover(width, height, box_width, box_height);
+            within_budget("thumbnail", size, Size::Percent(fit).dimensions(width, height))?;
+            img.thumbnail(box_width, box_height)
+        }
+        size => {
+            let (new_width, new_height) = within_budget("thumbnail", size, size.dimensions(width, height))?;
+            img.thumbnail_exact(new_width, new_height)
+        }
+    })
+}
@@ -0,0 +1,39 @@
+//! SplitMix64, the small pseudo-random generator behind jittered samples, the
+//! chaos game and noise lattices. It is reproducible across platforms, so a
+//! seed always gives the same image.
+
+/// The golden-ratio increment SplitMix64 steps its state by, also handy for
+/// spreading coordinates before they are mixed.
+pub const GOLDEN: u64 = 0x9e3779b97f4a7c15;
+
+/// SplitMix64's finalizer: scrambles `h` so that nearby inputs give unrelated
+/// outputs.
+pub fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+/// The top 53 bits of `h` as a number in `[0, 1)`.
+pub fn unit(h: u64) -> f64 {
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// A stream of pseudo-random numbers starting from a seed.
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(GOLDEN);
+        mix(self.0)
+    }
+
+    /// A number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        unit(self.next_u64())
+    }
+}
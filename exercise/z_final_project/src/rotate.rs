@@ -0,0 +1,314 @@
+//! Rotation by any angle.
+//!
+//! Quarter turns are done exactly by moving pixels. Other angles map every
+//! output pixel back into the source and interpolate, treating everything
+//! outside the source as the fill color. Interpolation runs on premultiplied
+//! colors, so edges blend smoothly into a transparent fill instead of
+//! darkening.
+
+use std::fmt;
+use std::str::FromStr;
+use image::{imageops, ColorType, DynamicImage, Rgba32FImage};
+use crate::palette::{format_color, parse_color};
+use crate::{Argument, Arguments, MirageError, Result, Rotation};
+
+/// How colors between source pixels are estimated.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Interpolation {
+    /// The closest pixel; keeps hard pixel edges.
+    Nearest,
+    /// Linear between the four surrounding pixels.
+    Bilinear,
+    /// Catmull-Rom spline through the sixteen surrounding pixels; sharper than
+    /// bilinear.
+    Bicubic,
+}
+
+impl FromStr for Interpolation {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(Interpolation::Nearest),
+            "bilinear" => Ok(Interpolation::Bilinear),
+            "bicubic" => Ok(Interpolation::Bicubic),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Interpolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Interpolation::Nearest => "nearest",
+            Interpolation::Bilinear => "bilinear",
+            Interpolation::Bicubic => "bicubic",
+        })
+    }
+}
+
+/// Size of the rotated image.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Canvas {
+    /// Grows the image so the whole rotated source fits.
+    Expand,
+    /// Keeps the size of the source, cutting off the rotated corners.
+    Crop,
+}
+
+impl FromStr for Canvas {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "expand" => Ok(Canvas::Expand),
+            "crop" => Ok(Canvas::Crop),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Canvas::Expand => "expand",
+            Canvas::Crop => "crop",
+        })
+    }
+}
+
+/// What the parts of the canvas the source doesn't cover are filled with.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Fill {
+    Color([u8; 3]),
+    /// Leaves them transparent, which gives the result an alpha channel.
+    Transparent,
+}
+
+impl FromStr for Fill {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "transparent" => Ok(Fill::Transparent),
+            _ => parse_color(s).map(Fill::Color).ok_or(()),
+        }
+    }
+}
+
+impl fmt::Display for Fill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fill::Color(color) => f.write_str(&format_color(*color)),
+            Fill::Transparent => f.write_str("transparent"),
+        }
+    }
+}
+
+/// Everything `rotate` needs to turn an image.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RotateParams {
+    /// Degrees clockwise.
+    pub angle: f64,
+    pub interpolation: Interpolation,
+    pub canvas: Canvas,
+    pub fill: Fill,
+}
+
+impl Default for RotateParams {
+    fn default() -> Self {
+        RotateParams {
+            angle: 90.0,
+            interpolation: Interpolation::Bilinear,
+            canvas: Canvas::Expand,
+            fill: Fill::Color([0, 0, 0]),
+        }
+    }
+}
+
+impl RotateParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::required("angle").with_hint("degrees clockwise"),
+        Argument::optional("interpolation", "bilinear").with_hint("nearest, bilinear, bicubic"),
+        Argument::optional("canvas", "expand").with_hint("expand, crop"),
+        Argument::optional("fill", "#000000").with_hint("#rrggbb or transparent"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let angle = args.value("angle");
+        Ok(RotateParams {
+            angle: angle
+                .parse()
+                .ok()
+                .filter(|angle: &f64| angle.is_finite())
+                .ok_or_else(|| MirageError::InvalidRotation(angle.to_string()))?,
+            interpolation: args
+                .value("interpolation")
+                .parse()
+                .map_err(|_| args.invalid("interpolation", "must be one of nearest, bilinear, bicubic"))?,
+            canvas: args
+                .value("canvas")
+                .parse()
+                .map_err(|_| args.invalid("canvas", "must be one of expand, crop"))?,
+            fill: args
+                .value("fill")
+                .parse()
+                .map_err(|_| args.invalid("fill", "must be a color like #ff8800 or transparent"))?,
+        })
+    }
+
+    /// The quarter turn `angle` amounts to, which needs no interpolation.
+    pub fn quarter_turn(&self) -> Option<Rotation> {
+        Rotation::from_degrees(self.angle)
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![
+            self.angle.to_string(),
+            self.interpolation.to_string(),
+            self.canvas.to_string(),
+            self.fill.to_string(),
+        ]
+    }
+}
+
+/// Sine and cosine of a clockwise angle, exact for quarter turns so they map
+/// pixel centers onto pixel centers.
+fn sin_cos(degrees: f64) -> (f64, f64) {
+    match degrees.rem_euclid(360.0) {
+        0.0 => (0.0, 1.0),
+        90.0 => (1.0, 0.0),
+        180.0 => (0.0, -1.0),
+        270.0 => (-1.0, 0.0),
+        angle => angle.to_radians().sin_cos(),
+    }
+}
+
+/// Catmull-Rom weights of the four samples around a position `t` past the
+/// second one.
+fn cubic_weights(t: f32) -> [f32; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    [
+        (-t3 + 2.0 * t2 - t) / 2.0,
+        (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+        (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+        (t3 - t2) / 2.0,
+    ]
+}
+
+/// Rotates `img` by `params.angle` degrees clockwise about its center.
+pub fn rotate(img: &DynamicImage, params: &RotateParams) -> DynamicImage {
+    let (width, height) = (img.width(), img.height());
+    let (sin, cos) = sin_cos(params.angle);
+    let fill = match params.fill {
+        Fill::Color(color) => [color[0] as f32 / 255.0, color[1] as f32 / 255.0, color[2] as f32 / 255.0, 1.0],
+        Fill::Transparent => [0.0; 4],
+    };
+    if params.angle.rem_euclid(360.0) == 0.0 {
+        return img.clone();
+    }
+    // Quarter turns are exact; on a cropped canvas of another shape the turned
+    // image is centered, rounding down where a half pixel would be left over.
+    if let Some(rotation) = params.quarter_turn() {
+        let turned = rotation.apply(img);
+        if rotation == Rotation::OneEighty || params.canvas == Canvas::Expand || width == height {
+            return turned;
+        }
+        let mut canvas = Rgba32FImage::from_pixel(width, height, image::Rgba(fill));
+        let x = (width as i64 - height as i64).div_euclid(2);
+        let y = (height as i64 - width as i64).div_euclid(2);
+        imageops::replace(&mut canvas, &turned.to_rgba32f(), x, y);
+        return with_color_of(canvas, img, params.fill);
+    }
+    let (out_width, out_height) = match params.canvas {
+        // The tolerance keeps rounding errors from adding a row or column.
+        Canvas::Expand => (
+            (width as f64 * cos.abs() + height as f64 * sin.abs() - 1e-6).ceil().max(1.0) as u32,
+            (width as f64 * sin.abs() + height as f64 * cos.abs() - 1e-6).ceil().max(1.0) as u32,
+        ),
+        Canvas::Crop => (width, height),
+    };
+
+    let source: Vec<[f32; 4]> = img
+        .to_rgba32f()
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            [r * a, g * a, b * a, a]
+        })
+        .collect();
+    let at = |x: i64, y: i64| {
+        if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+            source[(y * width as i64 + x) as usize]
+        } else {
+            fill
+        }
+    };
+    // Weighted sum of the samples at `xs` times `ys`.
+    let blend = |xs: &[(i64, f32)], ys: &[(i64, f32)]| {
+        let mut sum = [0.0f32; 4];
+        for &(y, y_weight) in ys {
+            for &(x, x_weight) in xs {
+                let sample = at(x, y);
+                for channel in 0..4 {
+                    sum[channel] += x_weight * y_weight * sample[channel];
+                }
+            }
+        }
+        sum
+    };
+
+    let rotated = Rgba32FImage::from_fn(out_width, out_height, |x, y| {
+        // Offset of the output pixel center from the middle of the output,
+        // turned back into the source, in source pixel indices.
+        let dx = x as f64 + 0.5 - out_width as f64 / 2.0;
+        let dy = y as f64 + 0.5 - out_height as f64 / 2.0;
+        let u = dx * cos + dy * sin + width as f64 / 2.0 - 0.5;
+        let v = -dx * sin + dy * cos + height as f64 / 2.0 - 0.5;
+        let [r, g, b, a] = match params.interpolation {
+            Interpolation::Nearest => at(u.round() as i64, v.round() as i64),
+            Interpolation::Bilinear => {
+                let (x0, y0) = (u.floor(), v.floor());
+                let (tx, ty) = ((u - x0) as f32, (v - y0) as f32);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                blend(&[(x0, 1.0 - tx), (x0 + 1, tx)], &[(y0, 1.0 - ty), (y0 + 1, ty)])
+            }
+            Interpolation::Bicubic => {
+                let (x0, y0) = (u.floor(), v.floor());
+                let (wx, wy) = (cubic_weights((u - x0) as f32), cubic_weights((v - y0) as f32));
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let xs = [0, 1, 2, 3].map(|i| (x0 - 1 + i, wx[i as usize]));
+                let ys = [0, 1, 2, 3].map(|i| (y0 - 1 + i, wy[i as usize]));
+                // The spline overshoots near sharp edges.
+                let [r, g, b, a] = blend(&xs, &ys);
+                let a = a.clamp(0.0, 1.0);
+                [r.clamp(0.0, a), g.clamp(0.0, a), b.clamp(0.0, a), a]
+            }
+        };
+        let unpremultiply = |channel: f32| if a > 0.0 { channel / a } else { 0.0 };
+        image::Rgba([unpremultiply(r), unpremultiply(g), unpremultiply(b), a])
+    });
+
+    with_color_of(rotated, img, params.fill)
+}
+
+/// Converts a rotated image back to the color type of the `source`, keeping
+/// grayscale gray (a colored fill included) and the bit depth; a transparent
+/// fill adds an alpha channel.
+fn with_color_of(rotated: Rgba32FImage, source: &DynamicImage, fill: Fill) -> DynamicImage {
+    let rotated = DynamicImage::ImageRgba32F(rotated);
+    let alpha = source.color().has_alpha() || fill == Fill::Transparent;
+    match (source.color(), alpha) {
+        (ColorType::L8 | ColorType::La8, false) => DynamicImage::ImageLuma8(rotated.into_luma8()),
+        (ColorType::L8 | ColorType::La8, true) => DynamicImage::ImageLumaA8(rotated.into_luma_alpha8()),
+        (ColorType::L16 | ColorType::La16, false) => DynamicImage::ImageLuma16(rotated.into_luma16()),
+        (ColorType::L16 | ColorType::La16, true) => DynamicImage::ImageLumaA16(rotated.into_luma_alpha16()),
+        (ColorType::Rgb8 | ColorType::Rgba8, false) => DynamicImage::ImageRgb8(rotated.into_rgb8()),
+        (ColorType::Rgb8 | ColorType::Rgba8, true) => DynamicImage::ImageRgba8(rotated.into_rgba8()),
+        (ColorType::Rgb16 | ColorType::Rgba16, false) => DynamicImage::ImageRgb16(rotated.into_rgb16()),
+        (ColorType::Rgb16 | ColorType::Rgba16, true) => DynamicImage::ImageRgba16(rotated.into_rgba16()),
+        (_, false) => DynamicImage::ImageRgb32F(rotated.into_rgb32f()),
+        (_, true) => rotated,
+    }
+}
@@ -0,0 +1,132 @@
+//! Pipeline script files (`.mrg`).
+//!
+//! A script holds one command per line, written exactly as on the command line
+//! but without the `/` separators:
+//!
+//! ```text
+//! # soften and straighten the scan
+//! blur
+//! rotate 90
+//!
+//! crop 0 0 200 200
+//! brighten 10
+//! ```
+//!
+//! Blank lines are ignored, as are lines whose first non-blank character is `#`.
+//!
+//! Values with spaces are written in double quotes, in which `\"`, `\\` and
+//! `\n` stand for a quote, a backslash and a line break:
+//!
+//! ```text
+//! ifs system="my maps.txt"
+//! ```
+
+use std::fs;
+use crate::{ChainCommands, MirageError, Operation, Registry, Result};
+
+/// Parses script text into the same commands `Registry::parse` produces for
+/// the equivalent command line. Errors carry the 1-based line number.
+pub fn parse_script(script: &str, registry: &Registry) -> Result<Vec<ChainCommands>> {
+    let mut chain_commands = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let in_script = |err| MirageError::Script { line: index + 1, source: Box::new(err) };
+        let words = split_words(line).map_err(in_script)?;
+        let commands = registry.parse(&words).map_err(in_script)?;
+        chain_commands.extend(commands);
+    }
+    Ok(chain_commands)
+}
+
+/// Splits a script line into words at whitespace, keeping quoted parts of a
+/// word together.
+fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => word.push('\n'),
+                            Some(escaped) => word.push(escaped),
+                            None => return Err(unclosed_quote(line)),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(unclosed_quote(line)),
+                    }
+                }
+            }
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn unclosed_quote(line: &str) -> MirageError {
+    MirageError::InvalidArgument {
+        command: "script".to_string(),
+        value: line.to_string(),
+        reason: "a quote is never closed".to_string(),
+    }
+}
+
+/// A value as a single script word, quoted if it would not read back as one.
+fn quote(value: &str) -> String {
+    if !value.chars().any(|c| c.is_whitespace() || c == '"') {
+        return value.to_string();
+    }
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reads and parses a script file.
+pub fn load_script(path: &str, registry: &Registry) -> Result<Vec<ChainCommands>> {
+    parse_script(&fs::read_to_string(path)?, registry)
+}
+
+/// Formats commands as script text, one per line, so that `parse_script`
+/// gives the commands back.
+pub fn to_script(commands: &[ChainCommands]) -> String {
+    commands
+        .iter()
+        .map(|command| {
+            let mut words = vec![command.name().to_string()];
+            // An empty value would vanish between the spaces, so it is given
+            // by name instead.
+            for (arg, value) in command.arguments().iter().zip(command.values()) {
+                if value.is_empty() {
+                    words.push(format!("{}=", arg.name));
+                } else {
+                    words.push(quote(&value));
+                }
+            }
+            words.join(" ") + "\n"
+        })
+        .collect()
+}
+
+/// Writes commands to a script file.
+pub fn save_script(commands: &[ChainCommands], path: &str) -> Result<()> {
+    Ok(fs::write(path, to_script(commands))?)
+}
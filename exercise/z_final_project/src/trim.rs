@@ -0,0 +1,99 @@
+//! Removing uniform borders, such as the margins of scans.
+
+use std::fmt;
+use std::str::FromStr;
+use image::{DynamicImage, Rgba};
+use crate::crop::CropParams;
+use crate::{Argument, Arguments, Result};
+
+/// What counts as border.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Border {
+    /// Pixels matching the top-left corner.
+    Corner,
+    /// Fully transparent pixels.
+    Transparent,
+}
+
+impl FromStr for Border {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "corner" => Ok(Border::Corner),
+            "transparent" => Ok(Border::Transparent),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Border {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Border::Corner => "corner",
+            Border::Transparent => "transparent",
+        })
+    }
+}
+
+/// Everything `trim` needs to find the borders.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct TrimParams {
+    /// How far each channel (alpha included) may be from the corner color, or
+    /// the highest alpha counted as transparent, on a 0-255 scale.
+    pub tolerance: u8,
+    pub border: Border,
+}
+
+impl Default for TrimParams {
+    fn default() -> Self {
+        TrimParams { tolerance: 0, border: Border::Corner }
+    }
+}
+
+impl TrimParams {
+    pub const ARGUMENTS: &'static [Argument] = &[
+        Argument::optional("tolerance", "0").with_hint("0-255 per channel"),
+        Argument::optional("border", "corner").with_hint("corner, transparent"),
+    ];
+
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        Ok(TrimParams {
+            tolerance: args
+                .value("tolerance")
+                .parse()
+                .map_err(|_| args.invalid("tolerance", "must be between 0 and 255"))?,
+            border: args
+                .value("border")
+                .parse()
+                .map_err(|_| args.invalid("border", "must be one of corner, transparent"))?,
+        })
+    }
+
+    /// Argument values in schema order, see `Operation::values`.
+    pub fn values(&self) -> Vec<String> {
+        vec![self.tolerance.to_string(), self.border.to_string()]
+    }
+}
+
+/// The smallest rectangle holding everything but the border, as the `crop`
+/// that cuts it out, or `None` if the whole image is border.
+pub fn rectangle(img: &DynamicImage, params: &TrimParams) -> Option<CropParams> {
+    let rgba = img.to_rgba8();
+    let Rgba(corner) = *rgba.get_pixel_checked(0, 0)?;
+    let tolerance = params.tolerance;
+    let is_border = |Rgba(pixel): &Rgba<u8>| match params.border {
+        Border::Corner => pixel.iter().zip(corner).all(|(&channel, reference)| channel.abs_diff(reference) <= tolerance),
+        Border::Transparent => pixel[3] <= tolerance,
+    };
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in rgba.enumerate_pixels() {
+        if !is_border(pixel) {
+            let (left, top, right, bottom) = bounds.unwrap_or((x, y, x, y));
+            bounds = Some((left.min(x), top.min(y), right.max(x), bottom.max(y)));
+        }
+    }
+    bounds.map(|(left, top, right, bottom)| {
+        CropParams::pixels(left as i64, top as i64, right - left + 1, bottom - top + 1)
+    })
+}
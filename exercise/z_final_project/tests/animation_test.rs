@@ -0,0 +1,116 @@
+mod common;
+
+use std::fs::{self, File};
+use std::time::{Duration, Instant};
+use clap::Parser;
+use image::codecs::gif::GifDecoder;
+use image::AnimationDecoder;
+use num_complex::Complex;
+use mirage::animation::{render_frames, save_gif, ZoomParams};
+use mirage::fractal::FractalParams;
+use mirage::app::run;
+use mirage::pipeline::validate;
+use mirage::{split_command_vector, ChainCommands, Cli, MirageError, Registry};
+use common::words;
+
+#[test]
+fn zoom_frames_interpolate_exponentially_test() {
+    // given
+    let params = ZoomParams {
+        start: FractalParams { center: Complex::new(0.0, 0.0), zoom: 1.0, ..FractalParams::default() },
+        end_center: Complex::new(-0.5, 0.25),
+        end_zoom: 16.0,
+        frames: 5,
+        delay: 40,
+    };
+
+    // when
+    let frames = params.frame_params();
+
+    // then
+    let zooms: Vec<f64> = frames.iter().map(|frame| frame.zoom).collect();
+    assert_eq!(zooms, vec![1.0, 2.0, 4.0, 8.0, 16.0]);
+    assert_eq!(frames[0].center, Complex::new(0.0, 0.0));
+    assert!((frames[4].center - Complex::new(-0.5, 0.25)).norm() < 1e-12);
+    // Half of the visible width is gone after the first doubling, so the
+    // center has covered 8/15 of the way there.
+    assert!((frames[1].center.re - -0.5 * 8.0 / 15.0).abs() < 1e-12);
+}
+
+#[test]
+fn zoom_command_parses_with_fractal_arguments_test() {
+    // given
+    let commands = words("zoom 64 48 family=mandelbrot to=-0.75+0.1i to-zoom=50 frames=3 delay=20");
+
+    // when
+    let result = split_command_vector(&commands).unwrap();
+
+    // then
+    let ChainCommands::Zoom { params } = &result[0] else { panic!("expected zoom") };
+    assert_eq!((params.start.width, params.start.height), (64, 48));
+    assert_eq!(params.end_center, Complex::new(-0.75, 0.1));
+    assert_eq!((params.end_zoom, params.frames, params.delay), (50.0, 3, 20));
+    assert_eq!(split_command_vector(&words("zoom frames=1")).unwrap_err().exit_code(), 13);
+}
+
+#[test]
+fn zoom_frames_are_bounded_before_rendering_test() {
+    // given
+    let too_many = words("zoom 2 2 frames=4000000000");
+    let too_large = split_command_vector(&words("zoom 2000 2000 frames=1000")).unwrap();
+
+    // when
+    let parsed = split_command_vector(&too_many);
+    let validated = validate(false, &too_large);
+
+    // then
+    assert!(matches!(parsed, Err(MirageError::InvalidArgument { value, .. }) if value == "4000000000"));
+    // Every frame fits, but all of them together are over the pixel budget.
+    assert!(matches!(validated, Err(MirageError::InvalidArgument { value, .. }) if value == "2000x2000x1000"));
+}
+
+#[test]
+fn save_gif_writes_every_frame_test() {
+    // given
+    let params = ZoomParams {
+        start: FractalParams { width: 16, height: 12, iterations: 30, ..FractalParams::default() },
+        frames: 4,
+        ..ZoomParams::default()
+    };
+    let path = std::env::temp_dir().join(format!("mirage_zoom_{}.gif", std::process::id()));
+
+    // when
+    save_gif(render_frames(&params).map(Ok), params.delay, path.to_str().unwrap()).unwrap();
+
+    // then
+    let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0].buffer().dimensions(), (16, 12));
+    assert_eq!(frames[0].delay().numer_denom_ms(), (100, 1));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn save_gif_requires_gif_extension_test() {
+    // when
+    let result = save_gif(std::iter::empty(), 100, "zoom.png");
+
+    // then
+    assert!(matches!(result, Err(MirageError::InvalidArgument { .. })));
+}
+
+#[test]
+fn zoom_checks_outfile_before_rendering_test() {
+    // given
+    let args = ["mirage", "zoom.png", "zoom", "width=4000", "height=4000", "frames=60"];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    // when
+    let started = Instant::now();
+    let result = run(&cli, &Registry::default());
+
+    // then
+    assert!(matches!(result, Err(MirageError::InvalidArgument { value, .. }) if value == "zoom.png"));
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
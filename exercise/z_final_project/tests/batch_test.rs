@@ -0,0 +1,155 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use image::{DynamicImage, GenericImageView};
+use mirage::batch::{collect_inputs, output_path, plan_outputs, run_batch, DEFAULT_TEMPLATE};
+use mirage::rotate::RotateParams;
+use mirage::{ChainCommands, MirageError};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mirage_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn output_path_fills_template_test() {
+    // given
+    let input = Path::new("assets/hero.final.png");
+
+    // when
+    let result = output_path(input, Path::new("out"), "{stem}_processed.{ext}");
+
+    // then
+    assert_eq!(result, Path::new("out/hero.final_processed.png"));
+}
+
+#[test]
+fn run_batch_collects_failures_and_continues_test() {
+    // given
+    let dir = scratch_dir("batch");
+    DynamicImage::new_rgb8(4, 2).save(dir.join("a.png")).unwrap();
+    fs::write(dir.join("b.png"), b"not an image").unwrap();
+    DynamicImage::new_rgb8(6, 2).save(dir.join("c.png")).unwrap();
+    fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+    let commands = vec![ChainCommands::Rotate { params: RotateParams::default() }];
+
+    // when
+    let inputs = collect_inputs(&[dir.to_str().unwrap().to_string()]).unwrap();
+    let report = run_batch(&inputs, &dir.join("out"), "{stem}_processed.{ext}", &commands, 1).unwrap();
+
+    // then
+    assert_eq!(inputs.len(), 3);
+    assert_eq!(report.succeeded.len(), 2);
+    assert_eq!(report.failed.len(), 1);
+    assert!(report.failed[0].0.ends_with("b.png"));
+    assert!(matches!(report.failed[0].1, MirageError::Decode(_)));
+    let rotated = image::open(dir.join("out/c_processed.png")).unwrap();
+    assert_eq!(rotated.dimensions(), (2, 6));
+    assert!(report.summary().starts_with("2 of 3 files processed, 1 failed:"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn collect_inputs_expands_glob_test() {
+    // given
+    let dir = scratch_dir("glob");
+    for name in ["x.png", "y.png", "z.jpg"] {
+        DynamicImage::new_rgb8(1, 1).save(dir.join(name)).unwrap();
+    }
+
+    // when
+    let result = collect_inputs(&[
+        format!("{}/z.jpg", dir.display()),
+        format!("{}/*.png", dir.display()),
+    ]).unwrap();
+
+    // then
+    assert_eq!(result, vec![dir.join("z.jpg"), dir.join("x.png"), dir.join("y.png")]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn collect_inputs_names_sources_that_match_nothing_test() {
+    // given
+    let dir = scratch_dir("empty");
+    DynamicImage::new_rgb8(1, 1).save(dir.join("x.png")).unwrap();
+    fs::create_dir(dir.join("empty")).unwrap();
+    let sources = [
+        format!("{}/*.png", dir.display()),
+        format!("{}/*.jgp", dir.display()),
+        format!("{}/empty", dir.display()),
+    ];
+
+    // when
+    let result = collect_inputs(&sources);
+
+    // then
+    let err = result.unwrap_err();
+    assert!(matches!(&err, MirageError::NoInputs { sources: empty } if *empty == sources[1..]), "{:?}", err);
+    assert_eq!(err.exit_code(), 18);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn run_batch_in_parallel_keeps_input_order_test() {
+    // given
+    let dir = scratch_dir("parallel");
+    let mut inputs = Vec::new();
+    for index in 0..12 {
+        let input = dir.join(format!("{:02}.png", index));
+        DynamicImage::new_rgb8(index + 1, 1).save(&input).unwrap();
+        inputs.push(input);
+    }
+    fs::write(dir.join("05.png"), b"broken").unwrap();
+    let commands = vec![ChainCommands::Invert {}];
+
+    // when
+    let report = run_batch(&inputs, &dir.join("out"), "{name}", &commands, 4).unwrap();
+
+    // then
+    let succeeded: Vec<&PathBuf> = report.succeeded.iter().map(|(input, _)| input).collect();
+    let expected: Vec<&PathBuf> = inputs.iter().filter(|input| !input.ends_with("05.png")).collect();
+    assert_eq!(succeeded, expected);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(image::open(dir.join("out/11.png")).unwrap().dimensions(), (12, 1));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn run_batch_refuses_inputs_sharing_an_output_test() {
+    // given
+    let dir = scratch_dir("duplicate");
+    DynamicImage::new_rgb8(1, 1).save(dir.join("a.png")).unwrap();
+    DynamicImage::new_rgb8(1, 1).save(dir.join("a.jpg")).unwrap();
+    let inputs = collect_inputs(&[format!("{}/a.*", dir.display())]).unwrap();
+
+    // when
+    let planned = plan_outputs(&inputs, &dir.join("out"), "{name}").unwrap();
+    let err = run_batch(&inputs, &dir.join("out"), "{stem}.png", &[ChainCommands::Invert {}], 2).unwrap_err();
+
+    // then
+    assert_eq!(planned, vec![dir.join("out/a.jpg"), dir.join("out/a.png")]);
+    assert!(matches!(&err, MirageError::DuplicateOutput { output, .. } if *output == dir.join("out/a.png")), "{:?}", err);
+    assert_eq!(err.exit_code(), 16);
+    assert!(!dir.join("out").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn run_batch_never_overwrites_inputs_test() {
+    // given
+    let dir = scratch_dir("in_place");
+    DynamicImage::new_rgb8(1, 1).save(dir.join("a.png")).unwrap();
+    let inputs = collect_inputs(&[dir.to_str().unwrap().to_string()]).unwrap();
+
+    // when
+    let report = run_batch(&inputs, &dir, DEFAULT_TEMPLATE, &[ChainCommands::Invert {}], 1).unwrap();
+    let err = run_batch(&inputs, &dir.join("."), "{name}", &[ChainCommands::Invert {}], 1).unwrap_err();
+
+    // then
+    assert_eq!(report.succeeded, vec![(dir.join("a.png"), dir.join("a_out.png"))]);
+    assert!(matches!(&err, MirageError::OutputIsInput { input, .. } if *input == dir.join("a.png")), "{:?}", err);
+    assert_eq!(image::open(dir.join("a.png")).unwrap().to_rgb8().get_pixel(0, 0).0, [0, 0, 0]);
+    fs::remove_dir_all(&dir).unwrap();
+}
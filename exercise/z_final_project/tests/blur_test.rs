@@ -0,0 +1,107 @@
+mod common;
+
+use image::{DynamicImage, GenericImageView, Rgb32FImage, RgbaImage};
+use mirage::blur::{blur, Kernel};
+use mirage::script::to_script;
+use mirage::{split_command_vector, ChainCommands, MirageError};
+use common::words;
+
+#[test]
+fn kernels_spread_a_point_by_sigma_test() {
+    // given
+    let mut point = Rgb32FImage::new(81, 81);
+    point.put_pixel(40, 40, image::Rgb([1.0, 1.0, 1.0]));
+    let point = DynamicImage::ImageRgb32F(point);
+
+    for kernel in [Kernel::Gaussian, Kernel::Box, Kernel::Stack] {
+        // when
+        let result = blur(&point, 5.0, kernel).into_rgb32f();
+
+        // then
+        let weights: Vec<f64> = (0..81).map(|x| (0..81).map(|y| result.get_pixel(x, y).0[0] as f64).sum()).collect();
+        let total: f64 = weights.iter().sum();
+        let variance: f64 = weights.iter().enumerate().map(|(x, w)| w * (x as f64 - 40.0).powi(2)).sum::<f64>() / total;
+        assert!((total - 1.0).abs() < 1e-4, "{} keeps brightness, got {}", kernel, total);
+        assert!((variance.sqrt() - 5.0).abs() < 0.5, "{} spreads by sigma, got {}", kernel, variance.sqrt());
+        let at = |x| result.get_pixel(x, 40).0[0];
+        assert!(at(40) > at(45));
+        assert!((at(35) - at(45)).abs() < 1e-6, "{} is symmetric", kernel);
+    }
+}
+
+#[test]
+fn blur_keeps_flat_images_and_color_type_test() {
+    // given
+    let flat = DynamicImage::ImageRgba8(RgbaImage::from_pixel(30, 20, image::Rgba([200, 100, 50, 128])));
+
+    for kernel in [Kernel::Gaussian, Kernel::Box, Kernel::Stack] {
+        // when
+        let result = blur(&flat, 12.0, kernel);
+
+        // then
+        assert_eq!(result, flat, "{}", kernel);
+    }
+    assert_eq!(blur(&flat, 0.0, Kernel::Box), flat);
+}
+
+#[test]
+fn blur_parses_sigma_and_kernel_test() {
+    // given
+    let chain = "blur / blur 3.5 box / blur kernel=stack";
+
+    // when
+    let commands = split_command_vector(&words(chain)).unwrap();
+    let errors: Vec<MirageError> = ["blur -1", "blur 2 median", "blur inf"]
+        .iter()
+        .map(|chain| split_command_vector(&words(chain)).unwrap_err())
+        .collect();
+
+    // then
+    assert_eq!(
+        commands,
+        vec![
+            ChainCommands::Blur { sigma: 2.0, kernel: Kernel::Gaussian },
+            ChainCommands::Blur { sigma: 3.5, kernel: Kernel::Box },
+            ChainCommands::Blur { sigma: 2.0, kernel: Kernel::Stack },
+        ]
+    );
+    assert_eq!(to_script(&commands), "blur 2 gaussian\nblur 3.5 box\nblur 2 stack\n");
+    assert!(errors.iter().all(|error| matches!(error, MirageError::InvalidArgument { .. })), "{:?}", errors);
+}
+
+#[test]
+fn fast_kernels_handle_radii_larger_than_the_image_test() {
+    // given
+    let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(200, 3, |x, _| image::Rgb([x as u8, 0, 255])));
+
+    for kernel in [Kernel::Box, Kernel::Stack] {
+        // when
+        let result = blur(&image, 400.0, kernel);
+
+        // then
+        // Most of the weight falls on the repeated edge pixels, which flattens
+        // the gradient toward its middle.
+        assert_eq!(result.dimensions(), (200, 3));
+        let left = result.get_pixel(0, 1).0;
+        let right = result.get_pixel(199, 1).0;
+        assert!((70..=130).contains(&left[0]) && (70..=130).contains(&right[0]), "{} {:?} {:?}", kernel, left, right);
+        assert_eq!(left[2], 255);
+    }
+}
+
+#[test]
+fn huge_sigmas_blur_small_images_quickly_test() {
+    // given
+    let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(40, 20, |x, _| image::Rgb([x as u8 * 6, 0, 255])));
+
+    for kernel in ["gaussian", "box", "stack"] {
+        // when
+        let commands = split_command_vector(&words(&format!("blur 1e12 {}", kernel))).unwrap();
+        let result = mirage::pipeline::execute(Some(image.clone()), &commands).unwrap().unwrap();
+
+        // then
+        // Only the repeated edge pixels still count, evenly on both sides.
+        assert_eq!(result.dimensions(), (40, 20));
+        assert!(result.pixels().all(|(_, _, pixel)| (116..=118).contains(&pixel.0[0]) && pixel.0[2] == 255), "{}", kernel);
+    }
+}
@@ -1,5 +1,7 @@
-use std::panic::catch_unwind;
-use mirage::{split_command_vector, ChainCommands, Rotation};
+use clap::Parser;
+use mirage::blur::Kernel;
+use mirage::rotate::RotateParams;
+use mirage::{split_command_vector, ChainCommands, Cli, MirageError};
 
 #[test]
 fn split_commands_vector_happy_path_test() {
@@ -7,12 +9,12 @@ fn split_commands_vector_happy_path_test() {
     let commands: Vec<String> = "blur / rotate 90".split_whitespace().map(String::from).collect();
 
     // when
-    let result = split_command_vector(&commands);
+    let result = split_command_vector(&commands).unwrap();
 
     // then
     let expected_result = vec![
-        ChainCommands::Blur {},
-        ChainCommands::Rotate { rotation: Rotation::Ninety }
+        ChainCommands::Blur { sigma: 2.0, kernel: Kernel::Gaussian },
+        ChainCommands::Rotate { params: RotateParams::default() }
     ];
     assert_eq!(result, expected_result);
 }
@@ -23,11 +25,78 @@ fn split_commands_vector_wrong_number_of_arguments_test() {
     let commands: Vec<String> = "blur / rotate".split_whitespace().map(String::from).collect();
 
     // when
-    let result = catch_unwind(|| split_command_vector(&commands));
+    let result = split_command_vector(&commands);
+
+    // then
+    let err = result.unwrap_err();
+    assert!(matches!(&err, MirageError::WrongArity { command, .. } if command == "rotate"));
+    assert_eq!(err.to_string(), "wrong number of arguments, usage: rotate <angle (degrees clockwise)> [interpolation=bilinear (nearest, bilinear, bicubic)] [canvas=expand (expand, crop)] [fill=#000000 (#rrggbb or transparent)]");
+}
+
+#[test]
+fn wrong_number_of_arguments_without_arguments_test() {
+    // given
+    let commands: Vec<String> = "invert 1".split_whitespace().map(String::from).collect();
+
+    // when
+    let err = split_command_vector(&commands).unwrap_err();
+
+    // then
+    assert_eq!(err.to_string(), "wrong number of arguments, usage: invert");
+}
+
+#[test]
+fn split_commands_vector_errors_test() {
+    // given
+    let cases = [
+        ("sharpen", 3),
+        ("brighten ten", 5),
+        ("rotate left", 6),
+    ];
+
+    for (chain, exit_code) in cases {
+        let commands: Vec<String> = chain.split_whitespace().map(String::from).collect();
+
+        // when
+        let result = split_command_vector(&commands);
+
+        // then
+        assert_eq!(result.unwrap_err().exit_code(), exit_code, "{}", chain);
+    }
+}
+
+#[test]
+fn negative_values_need_no_separator_test() {
+    // given
+    let args = ["mirage", "--infile", "in.png", "out.png", "brighten", "-5", "/", "crop", "-10%", "-20", "50%", "50%"];
+
+    // when
+    let cli = Cli::try_parse_from(Cli::options_first(args)).unwrap();
+
+    // then
+    assert_eq!(cli.infile.as_deref(), Some("in.png"));
+    assert_eq!(cli.command_vector, ["brighten", "-5", "/", "crop", "-10%", "-20", "50%", "50%"]);
+    assert_eq!(split_command_vector(&cli.command_vector).unwrap().len(), 2);
+}
+
+#[test]
+fn options_after_the_chain_are_still_options_test() {
+    // given
+    let single = ["mirage", "out.png", "crop", "-10%", "-20", "5", "5", "--infile", "in.png", "--save-script=chain.mrg"];
+    let batch = ["mirage", "out/", "invert", "--batch", "a/", "--batch", "b/", "--jobs", "2"];
+
+    // when
+    let single = Cli::try_parse_from(Cli::options_first(single)).unwrap();
+    let batch = Cli::try_parse_from(Cli::options_first(batch)).unwrap();
 
     // then
-    assert!(result.is_err());
-    let binding = result.unwrap_err();
-    let err = binding.downcast_ref::<String>();
-    assert_eq!(err.unwrap(), "Rotate <rotation (90, 180, 270)>");
+    assert_eq!(single.outfile, "out.png");
+    assert_eq!(single.command_vector, ["crop", "-10%", "-20", "5", "5"]);
+    assert_eq!(single.infile.as_deref(), Some("in.png"));
+    assert_eq!(single.save_script.as_deref(), Some("chain.mrg"));
+    assert_eq!(batch.command_vector, ["invert"]);
+    assert_eq!(batch.batch, ["a/", "b/"]);
+    assert_eq!(batch.jobs, Some(2));
+    let escaped = Cli::try_parse_from(Cli::options_first(["mirage", "out.png", "--", "invert", "--infile"])).unwrap();
+    assert_eq!(escaped.command_vector, ["invert", "--infile"]);
 }
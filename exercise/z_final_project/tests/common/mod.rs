@@ -0,0 +1,26 @@
+//! Helpers shared by the integration tests.
+
+// Each test file is its own crate and uses only some of these.
+#![allow(dead_code)]
+
+use num_complex::Complex;
+use mirage::{split_command_vector, MirageError};
+
+/// A chain as the shell hands it to mirage, one word per argument.
+pub fn words(chain: &str) -> Vec<String> {
+    chain.split_whitespace().map(String::from).collect()
+}
+
+/// The offending value and the reason of the `InvalidArgument` error `chain`
+/// fails to parse with.
+pub fn rejection(chain: &str) -> (String, String) {
+    match split_command_vector(&words(chain)) {
+        Err(MirageError::InvalidArgument { value, reason, .. }) => (value, reason),
+        result => panic!("{} was not rejected as invalid: {:?}", chain, result),
+    }
+}
+
+/// Whether two complex numbers agree up to rounding.
+pub fn close(a: Complex<f64>, b: Complex<f64>) -> bool {
+    (a - b).norm() < 1e-9
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use mirage::crop::{crop, CropParams, Gravity, Length};
+use mirage::pipeline::execute;
+use mirage::script::to_script;
+use mirage::{split_command_vector, ChainCommands, MirageError};
+use common::{rejection, words};
+
+fn rectangle(chain: &str) -> mirage::Result<(u32, u32, u32, u32)> {
+    match split_command_vector(&words(chain))?.remove(0) {
+        mirage::ChainCommands::Crop { params } => params.rectangle(200, 100),
+        command => panic!("not a crop: {:?}", command),
+    }
+}
+
+#[test]
+fn rectangles_resolve_offsets_and_gravity_test() {
+    // given
+    let cases = [
+        ("crop 10 20 30 40", (10, 20, 30, 40)),
+        ("crop -30 -40 30 40", (170, 60, 30, 40)),
+        ("crop 10% -50% 50% 50%", (20, 50, 100, 50)),
+        ("crop width=50 height=20 gravity=center", (75, 40, 50, 20)),
+        ("crop width=50 height=20 gravity=south-east", (150, 80, 50, 20)),
+        ("crop -5 5 50 20 north-east", (145, 5, 50, 20)),
+        ("crop 0 0 100% 100% south", (0, 0, 200, 100)),
+    ];
+
+    for (chain, expected) in cases {
+        // when
+        let result = rectangle(chain);
+
+        // then
+        assert_eq!(result.unwrap(), expected, "{}", chain);
+    }
+}
+
+#[test]
+fn rectangles_outside_the_image_are_rejected_test() {
+    // given
+    let image = DynamicImage::ImageRgb8(RgbImage::new(200, 100));
+    let commands = split_command_vector(&words("crop 190 0 20 10")).unwrap();
+
+    // when
+    let err = execute(Some(image), &commands).unwrap_err();
+
+    // then
+    assert!(matches!(err, MirageError::CropOutOfBounds { x: 190, width: 20, image_width: 200, .. }));
+    assert_eq!(err.exit_code(), 15);
+    assert_eq!(err.to_string(), "crop rectangle 20x10 at (190, 0) does not fit in the 200x100 image");
+    let huge = [
+        "crop 9223372036854775807 0 1 1",
+        "crop 0 -9223372036854775808 1 1",
+        "crop 0 0 9223372036854775807 1 east",
+        "crop -9223372036854775808 0 1 1 center",
+    ];
+    for chain in ["crop -10 0 20 10", "crop 0 0 101% 10", "crop 5 0 200 10 gravity=center", "crop 0 -101 10 10"].iter().chain(&huge) {
+        assert!(matches!(rectangle(chain), Err(MirageError::CropOutOfBounds { .. })), "{}", chain);
+    }
+}
+
+#[test]
+fn crop_cuts_out_the_rectangle_test() {
+    // given
+    let image = DynamicImage::ImageRgb8(RgbImage::from_fn(4, 3, |x, y| Rgb([x as u8, y as u8, 0])));
+    let params = CropParams { x: Length::Pixels(-2), y: Length::Pixels(-1), ..CropParams::pixels(0, 0, 2, 1) };
+    let centered = CropParams { gravity: Gravity::Center, ..CropParams::pixels(0, 0, 2, 1) };
+
+    // when
+    let result = crop(&image, &params).unwrap();
+    let middle = crop(&image, &centered).unwrap();
+
+    // then
+    assert_eq!(result.dimensions(), (2, 1));
+    assert_eq!(result.get_pixel(0, 0).0, [2, 2, 0, 255]);
+    assert_eq!(middle.get_pixel(0, 0).0, [1, 1, 0, 255]);
+}
+
+#[test]
+fn crop_scripts_keep_percentages_and_negative_offsets_test() {
+    // given
+    let commands = split_command_vector(&words("crop width=50% height=25% gravity=center / crop -10 -10% 5 5")).unwrap();
+
+    // when
+    let script = to_script(&commands);
+
+    // then
+    // Nothing is resolved against an image yet, so the script still fits any size.
+    let ChainCommands::Crop { params } = commands[1] else { panic!("expected crop") };
+    assert_eq!((params.x, params.y), (Length::Pixels(-10), Length::Percent(-10.0)));
+    assert_eq!(script, "crop 0 0 50% 25% center\ncrop -10 -10% 5 5 none\n");
+}
+
+#[test]
+fn crop_rejects_empty_sizes_and_unknown_gravities_test() {
+    // given
+    let cases = [
+        ("crop 0 0 0 10", "0", "must be positive"),
+        ("crop 0 0 10 -5", "-5", "must be positive"),
+        ("crop 0 0 10 0%", "0%", "must be positive"),
+        ("crop a 0 10 10", "a", "must be a number of pixels or a percentage like 25%"),
+        ("crop 0 0 10% 10 gravity=middle", "middle", "must be one of none, north-west"),
+    ];
+
+    for (chain, value, reason) in cases {
+        // when
+        let (rejected, why) = rejection(chain);
+
+        // then
+        assert_eq!(rejected, value, "{}", chain);
+        assert!(why.starts_with(reason), "{}: {}", chain, why);
+    }
+    assert!(matches!(split_command_vector(&words("crop 10 10")), Err(MirageError::WrongArity { .. })));
+}
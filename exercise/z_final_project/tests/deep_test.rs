@@ -0,0 +1,156 @@
+mod common;
+
+use num_complex::Complex;
+use mirage::deep::{self, Fixed};
+use mirage::fractal::{escape_time, render, Family, FractalParams, Precision};
+use mirage::palette::Palette;
+use mirage::script::to_script;
+use mirage::{split_command_vector, ChainCommands, MirageError};
+use common::words;
+
+#[test]
+fn fixed_point_arithmetic_is_exact_test() {
+    // given
+    let a = Fixed::from_f64(-0.743643887037151, 4);
+    let b = Fixed::from_f64(1e-30, 4);
+
+    // when
+    let sum = a.add(&b).sub(&a);
+    let square = a.mul(&a);
+    let negated = a.neg().mul(&Fixed::from_f64(2.0, 4));
+
+    // then
+    // 4 limbs resolve 2^-128, about 3e-39.
+    assert!((sum.to_f64() - 1e-30).abs() < 1e-38);
+    assert!((square.to_f64() - 0.743643887037151f64.powi(2)).abs() < 1e-16);
+    assert_eq!(negated.to_f64(), 2.0 * 0.743643887037151);
+}
+
+#[test]
+fn deep_matches_double_at_shallow_zoom_test() {
+    // given
+    let double = FractalParams {
+        width: 48,
+        height: 32,
+        family: Family::Mandelbrot,
+        iterations: 200,
+        center: Complex::new(-0.5, 0.0),
+        palette: Some(Palette::named("fire").unwrap()),
+        precision: Precision::Double,
+        ..FractalParams::default()
+    };
+    let deep = FractalParams { precision: Precision::Deep, ..double.clone() };
+
+    // when
+    let double_image = render(&double);
+    let deep_image = render(&deep);
+
+    // then
+    let matching = double_image.pixels().zip(deep_image.pixels()).filter(|(a, b)| a == b).count();
+    assert!(matching * 100 >= 99 * 48 * 32, "{} pixels match", matching);
+}
+
+#[test]
+fn deep_resolves_zooms_beyond_double_test() {
+    // given
+    let params = FractalParams {
+        width: 16,
+        height: 16,
+        family: Family::Mandelbrot,
+        iterations: 4000,
+        center: Complex::new(-1.7499, 0.0),
+        zoom: 1e18,
+        precision: Precision::Deep,
+        ..FractalParams::default()
+    };
+    let orbit = deep::reference_orbit(&params);
+    // Plain iteration in fixed point, with twice the limbs perturbation asks
+    // for; the pixel's c = center + offset is exact at that precision.
+    let exact = |offset: Complex<f64>| {
+        let frac = 2 * deep::precision_limbs(&params);
+        let c_re = Fixed::from_f64(params.center.re, frac).add(&Fixed::from_f64(offset.re, frac));
+        let c_im = Fixed::from_f64(params.center.im, frac).add(&Fixed::from_f64(offset.im, frac));
+        let two = Fixed::from_f64(2.0, frac);
+        let (mut re, mut im) = (Fixed::zero(frac), Fixed::zero(frac));
+        let mut n = 0;
+        while n < params.iterations && re.to_f64().hypot(im.to_f64()) <= 2.0 {
+            (re, im) = (re.mul(&re).sub(&im.mul(&im)).add(&c_re), two.mul(&re).mul(&im).add(&c_im));
+            n += 1;
+        }
+        (n < params.iterations).then_some(n)
+    };
+    let pixels = [(0, 0), (15, 0), (8, 8), (3, 11), (12, 5), (15, 15)];
+
+    for (x, y) in pixels {
+        let offset = params.offset(x as f64 + 0.5, y as f64 + 0.5);
+        let expected = exact(offset);
+
+        // when
+        let deep_result = deep::escape_time(offset, &orbit, &params).map(|value| value as u32);
+        let double_result = escape_time(params.center + offset, &params).map(|value| value as u32);
+
+        // then
+        assert!(expected.is_some(), "({}, {})", x, y);
+        assert_eq!(deep_result, expected, "({}, {})", x, y);
+        // Pixels 2e-19 apart are lost in f64 rounding of the center.
+        assert_ne!(double_result, expected, "({}, {})", x, y);
+    }
+}
+
+#[test]
+fn decimal_text_parses_exactly_into_fixed_point_test() {
+    // given
+    let (near, far) = ("-1.749900000000000002", "-1.7499");
+
+    // when
+    let difference = Fixed::parse(near, 4).unwrap().sub(&Fixed::parse(far, 4).unwrap());
+
+    // then
+    assert!((difference.to_f64() + 2e-18).abs() < 1e-36);
+    assert_eq!(Fixed::parse("+1.5e-3", 2).unwrap().to_f64(), 0.0015);
+    assert_eq!(Fixed::parse("25E2", 1).unwrap().to_f64(), 2500.0);
+    for text in ["", ".", "1.2.3", "1e", "0x10", "inf"] {
+        assert!(Fixed::parse(text, 2).is_none(), "{}", text);
+    }
+}
+
+#[test]
+fn deep_centers_keep_digits_beyond_double_test() {
+    // given
+    // The centers differ by 2e-18, about 10 pixels, but are the same `f64`.
+    let chain = |center: &str| {
+        words(&format!("fractal 16 16 family=mandelbrot iterations=4000 center={} zoom=1e18 precision=deep", center))
+    };
+    let near = split_command_vector(&chain("-1.749900000000000002+0i")).unwrap();
+    let far = split_command_vector(&chain("-1.7499")).unwrap();
+    let [ChainCommands::Fractal { params: near_params }, ChainCommands::Fractal { params: far_params }] =
+        [&near[0], &far[0]]
+    else {
+        panic!("fractal parses into ChainCommands::Fractal");
+    };
+
+    // when
+    let near_orbit = deep::reference_orbit(near_params);
+    let far_orbit = deep::reference_orbit(far_params);
+
+    // then
+    assert_eq!(near_params.center, far_params.center);
+    assert_ne!(near_orbit, far_orbit);
+    assert_eq!(split_command_vector(&words(&to_script(&near))).unwrap(), near);
+}
+
+#[test]
+fn precision_argument_parses_and_validates_test() {
+    // given
+    let commands = split_command_vector(&words("fractal 8 8 family=mandelbrot zoom=1e15 precision=deep")).unwrap();
+
+    // when
+    let script = to_script(&commands);
+
+    // then
+    assert_eq!(split_command_vector(&words(&script)).unwrap(), commands);
+    for chain in ["fractal precision=quad", "fractal family=burning-ship precision=deep"] {
+        let err = split_command_vector(&words(chain)).unwrap_err();
+        assert!(matches!(err, MirageError::InvalidArgument { .. }), "{}", chain);
+    }
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use image::GenericImageView;
+use num_complex::Complex;
+use mirage::domain::color;
+use mirage::expression::Expression;
+use mirage::pipeline::execute;
+use mirage::script::to_script;
+use mirage::{split_command_vector, MirageError};
+use common::{close, rejection, words};
+
+#[test]
+fn expression_follows_precedence_test() {
+    // given
+    let z = Complex::new(0.5, -2.0);
+    let cases: Vec<(&str, Complex<f64>)> = vec![
+        ("z^2 + 1", z * z + 1.0),
+        ("-z^2", -(z * z)),
+        ("2^3^2", Complex::new(512.0, 0.0)),
+        ("1 - z - 1", -z),
+        ("2z(z+i)/3", 2.0 * z * (z + Complex::i()) / 3.0),
+        ("exp(i*pi) + cos(z)*sin(z) - log(e)", Complex::new(-1.0, 0.0) + z.cos() * z.sin() - 1.0),
+    ];
+
+    for (source, expected) in cases {
+        // when
+        let expression: Expression = source.parse().unwrap();
+
+        // then
+        assert!(close(expression.eval(z), expected), "{} = {}", source, expression.eval(z));
+    }
+}
+
+#[test]
+fn expression_errors_say_where_test() {
+    // given
+    let sources = ["z +", "sin z", "(z", "z $ 2", "tan(z)"];
+
+    // when
+    let errors: Vec<String> = sources.iter().map(|s| s.parse::<Expression>().unwrap_err()).collect();
+
+    // then
+    assert_eq!(errors[0], "expected a number, name or '(' at end of expression");
+    assert_eq!(errors[1], "expected '(' after function name 'z' at position 4");
+    assert_eq!(errors[2], "expected ')' at end of expression");
+    assert_eq!(errors[3], "unexpected character '$' at position 2");
+    assert_eq!(errors[4], "unknown name 't' at position 1");
+}
+
+#[test]
+fn color_uses_hue_for_argument_and_lightness_for_modulus_test() {
+    // given
+    let zero = Complex::new(0.0, 0.0);
+    let one = Complex::new(1.0, 0.0);
+    let minus_one = Complex::new(-1.0, 0.0);
+    let huge = Complex::new(1e12, 0.0);
+
+    // when / then
+    assert_eq!(color(zero, false), [0, 0, 0]);
+    assert_eq!(color(one, false), [255, 0, 0]);
+    assert_eq!(color(minus_one, false), [0, 255, 255]);
+    assert_eq!(color(huge, false), [255, 255, 255]);
+}
+
+#[test]
+fn domain_command_renders_and_round_trips_test() {
+    // given
+    let commands = split_command_vector(&words("domain 40 40 expression=z zoom=1 contours=true")).unwrap();
+
+    // when
+    let result = execute(None, &commands).unwrap().unwrap();
+    let script = to_script(&commands);
+
+    // then
+    assert_eq!(result.dimensions(), (40, 40));
+    // f(z) = z is reddish on the positive real axis and cyan on the negative one.
+    let right = result.get_pixel(35, 20).0;
+    let left = result.get_pixel(4, 20).0;
+    assert!(right[0] > right[2] && left[2] > left[0]);
+    assert_eq!(split_command_vector(&words(&script)).unwrap(), commands);
+    let err = split_command_vector(&words("domain expression=z^")).unwrap_err();
+    assert!(matches!(err, MirageError::InvalidArgument { .. }));
+}
+
+#[test]
+fn contours_is_a_boolean_flag_test() {
+    // given
+    let chain = "domain contours=yes";
+
+    // when
+    let (value, reason) = rejection(chain);
+
+    // then
+    assert_eq!(value, "yes");
+    assert_eq!(reason, "must be true or false");
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use mirage::flip::{flip, orient, transpose, transverse, Axis};
+use mirage::script::to_script;
+use mirage::{split_command_vector, MirageError};
+use common::words;
+
+fn numbered() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(3, 2, |x, y| Rgb([x as u8, y as u8, 0])))
+}
+
+fn at(image: &DynamicImage, x: u32, y: u32) -> [u8; 2] {
+    let [r, g, ..] = image.get_pixel(x, y).0;
+    [r, g]
+}
+
+#[test]
+fn flips_mirror_along_their_axis_test() {
+    // given
+    let image = numbered();
+
+    // when
+    let horizontal = flip(&image, Axis::Horizontal);
+    let vertical = flip(&image, Axis::Vertical);
+    let transposed = transpose(&image);
+    let transversed = transverse(&image);
+
+    // then
+    assert_eq!(at(&horizontal, 0, 0), [2, 0]);
+    assert_eq!(at(&vertical, 0, 0), [0, 1]);
+    assert_eq!(transposed.dimensions(), (2, 3));
+    assert_eq!(at(&transposed, 1, 2), [2, 1]);
+    assert_eq!(at(&transposed, 0, 2), [2, 0]);
+    assert_eq!(transversed.dimensions(), (2, 3));
+    assert_eq!(at(&transversed, 0, 0), [2, 1]);
+    assert_eq!(at(&transversed, 1, 2), [0, 0]);
+}
+
+#[test]
+fn orient_undoes_every_exif_orientation_test() {
+    // given
+    let image = numbered();
+    // Orientations 6 and 8 undo each other; every other one undoes itself.
+    let inverse = [0, 1, 2, 3, 4, 5, 8, 7, 6];
+
+    for orientation in 1..=8u8 {
+        // when
+        let upright = orient(&image, orientation).unwrap();
+        let restored = orient(&upright, inverse[orientation as usize]).unwrap();
+
+        // then
+        assert_eq!(restored, image, "{}", orientation);
+        let swapped = orientation >= 5;
+        assert_eq!(upright.dimensions(), if swapped { (2, 3) } else { (3, 2) }, "{}", orientation);
+    }
+    assert_eq!(orient(&image, 5).unwrap(), transpose(&image));
+    assert_eq!(orient(&image, 7).unwrap(), transverse(&image));
+    assert_eq!(at(&orient(&image, 6).unwrap(), 1, 0), [0, 0]);
+    assert!(matches!(orient(&image, 9), Err(MirageError::InvalidArgument { value, .. }) if value == "9"));
+}
+
+#[test]
+fn flip_and_orient_parse_test() {
+    // given
+    let chain = "flip h / flip axis=v / transpose / transverse / orient 6";
+
+    // when
+    let script = to_script(&split_command_vector(&words(chain)).unwrap());
+    let errors: Vec<MirageError> = ["flip x", "orient 0", "orient 9"]
+        .iter()
+        .map(|chain| split_command_vector(&words(chain)).unwrap_err())
+        .collect();
+
+    // then
+    assert_eq!(script, "flip h\nflip v\ntranspose\ntransverse\norient 6\n");
+    assert!(errors.iter().all(|error| matches!(error, MirageError::InvalidArgument { .. })), "{:?}", errors);
+    assert!(matches!(split_command_vector(&words("orient six")), Err(MirageError::BadNumber { .. })));
+}
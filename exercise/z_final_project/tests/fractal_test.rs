@@ -0,0 +1,240 @@
+mod common;
+
+use std::time::{Duration, Instant};
+use image::GenericImageView;
+use num_complex::Complex;
+use mirage::fractal::{escape_time, format_complex, parse_complex, render, Family, FractalParams, Sampling};
+use mirage::pipeline::execute;
+use mirage::{split_command_vector, ChainCommands, MirageError};
+use common::words;
+
+#[test]
+fn parse_complex_forms_test() {
+    // given
+    let cases = [
+        ("-0.4+0.6i", Complex::new(-0.4, 0.6)),
+        ("0.285-0.01i", Complex::new(0.285, -0.01)),
+        ("1e-3+2e-3i", Complex::new(1e-3, 2e-3)),
+        ("-i", Complex::new(0.0, -1.0)),
+        ("2.5", Complex::new(2.5, 0.0)),
+        ("-0.75,0.1", Complex::new(-0.75, 0.1)),
+    ];
+
+    for (text, expected) in cases {
+        // when
+        let result = parse_complex(text);
+
+        // then
+        assert_eq!(result, Some(expected), "{}", text);
+        assert_eq!(parse_complex(&format_complex(expected)), Some(expected));
+    }
+    assert_eq!(parse_complex("1+2j"), None);
+}
+
+#[test]
+fn fractal_arguments_default_and_override_test() {
+    // given
+    let commands = words("fractal / fractal 64 32 zoom=4 center=-0.5+0.25i c=0.285+0.01i iterations=100");
+
+    // when
+    let result = split_command_vector(&commands).unwrap();
+
+    // then
+    assert_eq!(result[0], ChainCommands::Fractal { params: FractalParams::default() });
+    let expected = FractalParams {
+        width: 64,
+        height: 32,
+        family: Family::Julia,
+        c: Complex::new(0.285, 0.01),
+        exponent: 2,
+        iterations: 100,
+        center: Complex::new(-0.5, 0.25),
+        zoom: 4.0,
+        ..FractalParams::default()
+    };
+    assert_eq!(result[1], ChainCommands::Fractal { params: expected });
+}
+
+#[test]
+fn fractal_rejects_invalid_arguments_test() {
+    // given
+    let cases = ["fractal 0", "fractal zoom=-1", "fractal c=abc", "fractal family=newton", "fractal exponent=1",
+                 "fractal palette=sunset", "fractal smooth=yes", "fractal samples=5", "fractal sampling=poisson"];
+
+    for chain in cases {
+        // when
+        let result = split_command_vector(&words(chain));
+
+        // then
+        assert!(matches!(result, Err(MirageError::InvalidArgument { .. } | MirageError::BadNumber { .. })), "{}", chain);
+    }
+}
+
+#[test]
+fn fractal_renders_requested_size_test() {
+    // given
+    let commands = split_command_vector(&words("fractal 40 30 c=0 iterations=50")).unwrap();
+
+    // when
+    let result = execute(None, &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(result.dimensions(), (40, 30));
+    // With c = 0 the set is the unit disc: the middle never escapes.
+    assert_eq!(result.get_pixel(20, 15).0[1], 255);
+    // The corners are far outside and escape immediately.
+    assert_eq!(result.get_pixel(0, 0).0[1], 0);
+}
+
+#[test]
+fn fractal_families_test() {
+    // given
+    let chains = [
+        "fractal 31 31 family=mandelbrot center=-0.5",
+        "fractal 31 31 family=burning-ship center=-0.5",
+        "fractal 31 31 family=tricorn",
+        "fractal 31 31 family=multibrot exponent=3",
+    ];
+
+    for chain in chains {
+        let commands = split_command_vector(&words(chain)).unwrap();
+
+        // when
+        let result = execute(None, &commands).unwrap().unwrap();
+
+        // then
+        // The middle pixel sits near the origin, which belongs to all these sets.
+        assert_eq!(result.get_pixel(15, 15).0[1], 255, "{}", chain);
+        assert!(result.get_pixel(0, 0).0[1] <= 1, "{}", chain);
+    }
+}
+
+#[test]
+fn fractal_family_round_trips_through_values_test() {
+    // given
+    let commands = split_command_vector(&words("fractal family=burning-ship exponent=5")).unwrap();
+
+    // when
+    let script = mirage::script::to_script(&commands);
+
+    // then
+    assert_eq!(script, "fractal 800 800 burning-ship -0.4+0.6i 5 255 0+0i 1 classic false 0 1 grid single\n");
+    assert_eq!(split_command_vector(&words(&script)).unwrap(), commands);
+}
+
+#[test]
+fn fractal_smooth_coloring_removes_banding_test() {
+    // given
+    let banded = FractalParams { width: 64, height: 8, family: Family::Mandelbrot, center: Complex::new(-0.8, 0.3),
+                                 zoom: 6.0, iterations: 100, ..FractalParams::default() };
+    let smooth = FractalParams { smooth: true, ..banded.clone() };
+
+    // when
+    let banded_values: Vec<f64> = (0..64).filter_map(|x| escape_time(banded.point(x as f64, 4.0), &banded)).collect();
+    let smooth_values: Vec<f64> = (0..64).filter_map(|x| escape_time(smooth.point(x as f64, 4.0), &smooth)).collect();
+
+    // then
+    assert!(banded_values.iter().all(|value| value.fract() == 0.0));
+    assert!(smooth_values.iter().any(|value| value.fract() != 0.0));
+}
+
+#[test]
+fn fractal_palette_colors_inside_black_test() {
+    // given
+    let commands = split_command_vector(&words("fractal 21 21 c=0 palette=#000000:0,#ff0000:1 smooth=true")).unwrap();
+
+    // when
+    let result = execute(None, &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(result.get_pixel(10, 10).0, [0, 0, 0, 255]);
+    let corner = result.get_pixel(0, 0).0;
+    assert_eq!((corner[1], corner[2]), (0, 0));
+}
+
+#[test]
+fn fractal_threads_render_identical_bytes_test() {
+    // given
+    let single = FractalParams { width: 97, height: 61, family: Family::Mandelbrot, center: Complex::new(-0.5, 0.0),
+                                 palette: "fire".parse().ok(), smooth: true, threads: 1, samples: 2,
+                                 sampling: Sampling::Jitter, ..FractalParams::default() };
+    let parallel = FractalParams { threads: 5, ..single.clone() };
+
+    // when
+    let single_result = render(&single);
+    let parallel_result = render(&parallel);
+
+    // then
+    assert_eq!(single_result.as_raw(), parallel_result.as_raw());
+}
+
+#[test]
+fn fractal_threads_beyond_the_row_bands_are_not_started_test() {
+    // given
+    let params = FractalParams { width: 4, height: 4, threads: 5_000_000, ..FractalParams::default() };
+
+    // when
+    let started = Instant::now();
+    let result = render(&params);
+
+    // then
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(result.as_raw(), render(&FractalParams { threads: 1, ..params }).as_raw());
+}
+
+#[test]
+fn fractal_supersampling_softens_edges_test() {
+    // given
+    // A unit disc (c = 0) rendered in black and white.
+    let aliased = FractalParams { width: 40, height: 40, c: Complex::new(0.0, 0.0), zoom: 1.2, iterations: 20,
+                                  palette: "#ffffff:0,#ffffff:1".parse().ok(), ..FractalParams::default() };
+    let grid = FractalParams { samples: 4, ..aliased.clone() };
+    let jitter = FractalParams { samples: 3, sampling: Sampling::Jitter, ..aliased.clone() };
+
+    // when
+    let gray_levels = |params: &FractalParams| {
+        let mut levels: Vec<u8> = render(params).pixels().map(|pixel| pixel.0[0]).collect();
+        levels.sort();
+        levels.dedup();
+        levels.len()
+    };
+
+    // then
+    assert_eq!(gray_levels(&aliased), 2);
+    assert!(gray_levels(&grid) > 2);
+    assert!(gray_levels(&jitter) > 2);
+    assert_eq!(render(&jitter).as_raw(), render(&jitter).as_raw());
+}
+
+#[test]
+fn default_view_is_the_baseline_turned_upright_test() {
+    // given
+    // The original generator iterated in f32 with the real axis down the rows
+    // and the imaginary axis across the columns. The viewport puts the real
+    // axis across and the imaginary axis up, sampling pixel centers, so the
+    // default image is the original one turned a quarter counterclockwise.
+    let size = 120;
+    let scale = 3.0 / size as f64;
+    let baseline = |x: u32, y: u32| {
+        let re = (x as f64 + 0.5 - size as f64 / 2.0) * scale;
+        let im = -(y as f64 + 0.5 - size as f64 / 2.0) * scale;
+        let c = Complex::new(-0.4f32, 0.6f32);
+        let mut z = Complex::new(re as f32, im as f32);
+        let mut green = 0;
+        while green < 255 && z.norm() <= 2.0 {
+            z = z * z + c;
+            green += 1;
+        }
+        let red = (240 * x / size) as u8;
+        let blue = (240 * y / size) as u8;
+        [red, green, blue]
+    };
+
+    // when
+    let result = render(&FractalParams { width: size, height: size, ..FractalParams::default() });
+
+    // then
+    for (x, y, pixel) in result.enumerate_pixels() {
+        assert_eq!(pixel.0, baseline(x, y), "({}, {})", x, y);
+    }
+}
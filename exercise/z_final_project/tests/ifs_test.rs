@@ -0,0 +1,107 @@
+mod common;
+
+use std::fs;
+use std::process;
+use image::GenericImageView;
+use mirage::ifs::{named_system, parse_maps, Variation, MAX_POINTS, SYSTEM_NAMES};
+use mirage::pipeline::execute;
+use mirage::script::to_script;
+use mirage::{split_command_vector, MirageError};
+use common::{rejection, words};
+
+#[test]
+fn parse_maps_reads_weights_and_variations_test() {
+    // given
+    let text = "# corner maps\n0.5 0 0 0.5 0 0\n\n0.5 0 0 0.5 0.5 0 3 swirl\n";
+
+    // when
+    let maps = parse_maps(text).unwrap();
+
+    // then
+    assert_eq!(maps.len(), 2);
+    assert_eq!(maps[0].weight, 0.25);
+    assert_eq!(maps[0].variation, Variation::Linear);
+    assert_eq!(maps[1].coefficients, [0.5, 0.0, 0.0, 0.5, 0.5, 0.0]);
+    assert_eq!(maps[1].weight, 3.0);
+    assert_eq!(maps[1].variation, Variation::Swirl);
+    assert_eq!(parse_maps("1 0 0 1 0\n").unwrap_err(), "line 1: expected a b c d e f [weight] [variation]");
+    assert_eq!(parse_maps("\n1 0 0 1 0 x\n").unwrap_err(), "line 2: 'x' is not a number");
+    assert_eq!(parse_maps("1 0 0 1 0 0 1 wavy\n").unwrap_err(), "line 1: unknown variation 'wavy'");
+    assert!(parse_maps("1 0 0 1 0 0 0\n").is_err());
+    for name in SYSTEM_NAMES {
+        assert!(named_system(name).is_some(), "{}", name);
+    }
+}
+
+#[test]
+fn ifs_renders_sierpinski_with_empty_middle_test() {
+    // given
+    let commands = split_command_vector(&words("ifs 66 60 system=sierpinski points=200000 palette=grayscale")).unwrap();
+
+    // when
+    let result = execute(None, &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(result.dimensions(), (66, 60));
+    // The triangle fills the middle of the image with its tip up; the hole
+    // in its middle stays black while the corner triangles are hit.
+    let brightness = |x, y| result.get_pixel(x, y).0[0];
+    assert_eq!(brightness(33, 38), 0);
+    assert!(brightness(33, 5) > 0);
+    assert!(brightness(4, 55) > 0 && brightness(62, 55) > 0);
+}
+
+#[test]
+fn ifs_is_deterministic_per_seed_test() {
+    // given
+    let render = |chain: &str| {
+        let commands = split_command_vector(&words(chain)).unwrap();
+        execute(None, &commands).unwrap().unwrap().into_bytes()
+    };
+
+    // when
+    let first = render("ifs 40 40 points=20000 coloring=flame");
+    let again = render("ifs 40 40 points=20000 coloring=flame");
+    let other = render("ifs 40 40 points=20000 coloring=flame seed=7");
+
+    // then
+    assert_eq!(first, again);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn ifs_reads_maps_from_file_test() {
+    // given
+    let path = std::env::temp_dir().join(format!("mirage_ifs_maps_{}.txt", process::id()));
+    fs::write(&path, "0.5 0 0 0.5 0 0\n0.5 0 0 0.5 0.5 0\n0.5 0 0 0.5 0 0.5\n0.5 0 0 0.5 0.5 0.5\n").unwrap();
+    let chain = format!("ifs 20 20 system={} points=5000", path.display());
+
+    // when
+    let commands = split_command_vector(&words(&chain)).unwrap();
+    let result = execute(None, &commands).unwrap().unwrap();
+    let script = to_script(&commands);
+    let missing = split_command_vector(&words("ifs system=no_such_maps.txt")).unwrap_err();
+    fs::remove_file(&path).unwrap();
+
+    // then
+    // Four quarter-size copies of the unit square fill the whole square.
+    assert!(result.to_rgb8().pixels().filter(|pixel| pixel.0 == [0, 0, 0]).count() < 50);
+    assert!(script.starts_with("ifs 20 20 "));
+    assert!(matches!(missing, MirageError::InvalidArgument { .. }));
+}
+
+#[test]
+fn ifs_points_are_bounded_test() {
+    // given
+    let too_many = format!("ifs points={}", MAX_POINTS + 1);
+
+    // when
+    let (value, reason) = rejection(&too_many);
+    let (none, _) = rejection("ifs points=0");
+
+    // then
+    assert_eq!(value, (MAX_POINTS + 1).to_string());
+    assert_eq!(reason, "must be between 1 and 10000000000");
+    assert_eq!(none, "0");
+    assert!(split_command_vector(&words(&format!("ifs points={}", MAX_POINTS))).is_ok());
+}
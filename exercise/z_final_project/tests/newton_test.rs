@@ -0,0 +1,95 @@
+mod common;
+
+use image::GenericImageView;
+use num_complex::Complex;
+use mirage::newton::{basin, Polynomial};
+use mirage::pipeline::execute;
+use mirage::script::to_script;
+use mirage::{split_command_vector, ChainCommands, MirageError};
+use common::{close, rejection, words};
+
+#[test]
+fn polynomial_roots_and_coefficients_agree_test() {
+    // given
+    let roots = vec![Complex::new(2.0, 0.0), Complex::new(0.0, 1.0), Complex::new(-1.0, -1.0)];
+
+    // when
+    let coefficients = Polynomial::Roots(roots.clone()).coefficients();
+    let found = Polynomial::Coefficients(coefficients.clone()).roots();
+
+    // then
+    assert_eq!(coefficients.len(), 4);
+    assert!(close(coefficients[0], Complex::new(1.0, 0.0)));
+    for root in &roots {
+        assert!(found.iter().any(|&candidate| close(candidate, *root)), "{}", root);
+    }
+}
+
+#[test]
+fn basin_converges_to_nearest_root_test() {
+    // given
+    let polynomial = Polynomial::Roots(vec![Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0)]);
+    let coefficients = polynomial.coefficients();
+    let roots = polynomial.roots();
+
+    // when
+    let right = basin(Complex::new(0.7, 0.3), &coefficients, &roots, 50);
+    let left = basin(Complex::new(-3.0, -2.0), &coefficients, &roots, 50);
+    // On the imaginary axis z^2 - 1 keeps Newton's method on the axis forever.
+    let undecided = basin(Complex::new(0.0, 0.5), &coefficients, &roots, 50);
+
+    // then
+    assert_eq!(right.map(|(root, _)| root), Some(0));
+    assert_eq!(left.map(|(root, _)| root), Some(1));
+    assert_eq!(undecided, None);
+}
+
+#[test]
+fn newton_command_renders_one_color_per_root_test() {
+    // given
+    let commands = split_command_vector(&words("newton 60 60 roots=1,-1,1i,-1i zoom=1.5")).unwrap();
+
+    // when
+    let result = execute(None, &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(result.dimensions(), (60, 60));
+    let hue_of = |x, y| result.get_pixel(x, y).0;
+    // Pixels right next to two different roots converge at once, at full brightness.
+    assert_ne!(hue_of(50, 30), hue_of(10, 30));
+    assert_ne!(hue_of(30, 10), hue_of(30, 50));
+}
+
+#[test]
+fn newton_drops_leading_zero_coefficients_test() {
+    // given
+    let commands = split_command_vector(&words("newton 10 10 coefficients=0,1,0,-2")).unwrap();
+
+    // when
+    let script = to_script(&commands);
+
+    // then
+    let ChainCommands::Newton { params } = &commands[0] else { panic!("expected newton") };
+    let expected: Vec<Complex<f64>> = [1.0, 0.0, -2.0].iter().map(|&c| Complex::new(c, 0.0)).collect();
+    assert_eq!(params.polynomial, Polynomial::Coefficients(expected));
+    assert!(script.starts_with("newton 10 10 roots= 1+0i,0+0i,-2+0i "), "{}", script);
+}
+
+#[test]
+fn newton_needs_one_polynomial_of_degree_one_or_more_test() {
+    // given
+    let cases = [
+        ("newton roots=1 coefficients=1,1", "give either roots or coefficients, not both"),
+        ("newton coefficients=5", "the polynomial must have degree 1 or more"),
+        ("newton coefficients=0,0,5", "the polynomial must have degree 1 or more"),
+    ];
+
+    for (chain, reason) in cases {
+        // when
+        let (_, why) = rejection(chain);
+
+        // then
+        assert_eq!(why, reason, "{}", chain);
+    }
+    assert!(matches!(split_command_vector(&words("newton roots=1,x")), Err(MirageError::BadNumber { .. })));
+}
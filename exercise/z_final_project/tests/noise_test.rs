@@ -0,0 +1,82 @@
+mod common;
+
+use image::GenericImageView;
+use mirage::noise::{perlin, simplex, worley};
+use mirage::pipeline::execute;
+use mirage::script::to_script;
+use mirage::split_command_vector;
+use common::{rejection, words};
+
+#[test]
+fn noise_functions_stay_in_range_test() {
+    // given
+    let points: Vec<(f64, f64)> = (0..400).map(|i| (i as f64 * 0.37 - 70.0, i as f64 * 0.71 - 140.0)).collect();
+
+    // when
+    let lattice: Vec<f64> = (-3..3).map(|i| perlin(i as f64, (2 * i) as f64, 5)).collect();
+
+    // then
+    assert!(lattice.iter().all(|&value| value == 0.0));
+    for &(x, y) in &points {
+        assert!((-1.0..=1.0).contains(&perlin(x, y, 1)), "perlin {} {}", x, y);
+        assert!((-1.0..=1.0).contains(&simplex(x, y, 1)), "simplex {} {}", x, y);
+        assert!((0.0..=1.0).contains(&worley(x, y, 1)), "worley {} {}", x, y);
+    }
+    assert!(points.iter().any(|&(x, y)| perlin(x, y, 1) != perlin(x, y, 2)));
+}
+
+#[test]
+fn noise_is_deterministic_per_seed_test() {
+    // given
+    let render = |chain: &str| {
+        let commands = split_command_vector(&words(chain)).unwrap();
+        execute(None, &commands).unwrap().unwrap().into_bytes()
+    };
+
+    // when
+    let first = render("noise 32 32 kind=simplex seed=3");
+    let again = render("noise 32 32 kind=simplex seed=3 threads=2");
+    let other = render("noise 32 32 kind=simplex seed=4");
+
+    // then
+    assert_eq!(first, again);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn noise_renders_golden_pixels_test() {
+    // given
+    let commands = split_command_vector(&words("noise 16 8 scale=8 seed=42")).unwrap();
+
+    // when
+    let result = execute(None, &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(result.dimensions(), (16, 8));
+    let gray: Vec<u8> = [(0, 0), (5, 3), (15, 7)].iter().map(|&(x, y)| result.get_pixel(x, y).0[0]).collect();
+    assert_eq!(gray, [136, 146, 120]);
+    assert!(result.to_rgb8().pixels().all(|pixel| pixel.0[0] == pixel.0[1] && pixel.0[1] == pixel.0[2]));
+}
+
+#[test]
+fn noise_octaves_and_scale_are_bounded_test() {
+    // given
+    let cases = [
+        ("noise octaves=0", "0", "must be between 1 and 16"),
+        ("noise octaves=17", "17", "must be between 1 and 16"),
+        ("noise scale=0", "0", "must be a positive number"),
+        ("noise scale=inf", "inf", "must be a positive number"),
+        ("noise persistence=-0.5", "-0.5", "must be a positive number"),
+        ("noise kind=value", "value", "must be one of perlin, simplex, worley"),
+    ];
+
+    for (chain, value, reason) in cases {
+        // when
+        let result = rejection(chain);
+
+        // then
+        assert_eq!(result, (value.to_string(), reason.to_string()), "{}", chain);
+    }
+    let script = to_script(&split_command_vector(&words("noise 8 8 kind=worley octaves=16 scale=0.5")).unwrap());
+    assert!(script.starts_with("noise 8 8 worley 0.5 16 0.5 0 "), "{}", script);
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use image::{DynamicImage, GenericImageView, RgbImage};
+use mirage::pipeline::execute;
+use mirage::script::to_script;
+use mirage::{Argument, Arguments, ChainCommands, CustomOperation, MirageError, Operation, Registry, Result};
+use common::words;
+
+#[derive(Debug, Default)]
+struct Fill {
+    level: u8,
+}
+
+impl Operation for Fill {
+    fn name(&self) -> &str {
+        "fill"
+    }
+
+    fn arguments(&self) -> &[Argument] {
+        const ARGUMENTS: &[Argument] = &[Argument::optional("level", "255")];
+        ARGUMENTS
+    }
+
+    fn parse(&self, args: &Arguments) -> Result<ChainCommands> {
+        Ok(ChainCommands::Custom(CustomOperation::new(Fill { level: args.number("level")? })))
+    }
+
+    fn values(&self) -> Vec<String> {
+        vec![self.level.to_string()]
+    }
+
+    fn apply(&self, img: &DynamicImage) -> Result<DynamicImage> {
+        let (width, height) = img.dimensions();
+        let level = self.level;
+        Ok(DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, image::Rgb([level; 3]))))
+    }
+}
+
+#[test]
+fn registry_parses_and_runs_custom_operation_test() {
+    // given
+    let mut registry = Registry::default();
+    registry.register(Fill::default());
+
+    // when
+    let commands = registry.parse(&words("rotate 90 / fill level=7")).unwrap();
+    let result = execute(Some(DynamicImage::new_rgb8(4, 2)), &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(commands[1], ChainCommands::Custom(CustomOperation::new(Fill { level: 7 })));
+    assert_eq!(to_script(&commands), "rotate 90 bilinear expand #000000\nfill 7\n");
+    assert_eq!(result.dimensions(), (2, 4));
+    assert_eq!(result.get_pixel(0, 0).0, [7, 7, 7, 255]);
+}
+
+#[test]
+fn registry_checks_arity_against_schema_test() {
+    // given
+    let mut registry = Registry::empty();
+    registry.register(Fill::default());
+
+    // when
+    let too_many = registry.parse(&words("fill 1 2"));
+    let unknown = registry.parse(&words("blur"));
+
+    // then
+    assert!(matches!(too_many, Err(MirageError::WrongArity { usage, .. }) if usage == "[level=255]"));
+    assert!(matches!(unknown, Err(MirageError::UnknownCommand(name)) if name == "blur"));
+}
+
+#[test]
+fn registry_help_lists_every_operation_test() {
+    // given
+    let mut registry = Registry::default();
+    registry.register(Fill::default());
+
+    // when
+    let help = registry.help();
+
+    // then
+    assert!(help.contains("  crop [x=0 (negative counts from the right)] [y=0 (negative counts from the bottom)] <width> <height> "));
+    assert!(help.contains("  blur [sigma=2 (standard deviation in pixels)] [kernel=gaussian (gaussian, box, stack)]\n"));
+    assert!(help.contains("  fill [level=255]\n"));
+}
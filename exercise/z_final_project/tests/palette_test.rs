@@ -0,0 +1,40 @@
+use mirage::palette::{parse_color, Palette, PALETTE_NAMES};
+
+#[test]
+fn palette_parses_stops_test() {
+    // given
+    let text = "#ffffff:1,#000000:0,#ff8800:0.5";
+
+    // when
+    let palette: Palette = text.parse().unwrap();
+
+    // then
+    assert_eq!(palette.sample(0.0), [0, 0, 0]);
+    assert_eq!(palette.sample(0.5), [255, 136, 0]);
+    assert_eq!(palette.sample(0.75), [255, 196, 128]);
+    assert_eq!(palette.sample(2.0), [255, 255, 255]);
+    assert_eq!(palette.to_string(), "#000000:0,#ff8800:0.5,#ffffff:1");
+    assert_eq!(palette.to_string().parse::<Palette>().unwrap(), palette);
+}
+
+#[test]
+fn palette_rejects_malformed_stops_test() {
+    // given
+    let cases = ["#000000:0", "#000000:0,#fffff:1", "#000000:0,#ffffff:1.5", "#000000,#ffffff"];
+
+    for text in cases {
+        // when
+        let result = text.parse::<Palette>();
+
+        // then
+        assert!(result.is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn palette_names_all_resolve_test() {
+    for name in PALETTE_NAMES {
+        assert!(Palette::named(name).is_some(), "{}", name);
+    }
+    assert_eq!(parse_color("ff8800"), Some([255, 136, 0]));
+}
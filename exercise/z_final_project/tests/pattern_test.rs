@@ -0,0 +1,83 @@
+mod common;
+
+use image::{DynamicImage, GenericImageView};
+use mirage::pattern::{Mode, PatternParams};
+use mirage::pipeline::execute;
+use mirage::script::to_script;
+use mirage::{split_command_vector, ChainCommands, MirageError};
+use common::words;
+
+fn generate(chain: &str) -> DynamicImage {
+    execute(None, &split_command_vector(&words(chain)).unwrap()).unwrap().unwrap()
+}
+
+#[test]
+fn pattern_arguments_default_and_round_trip_test() {
+    // given
+    let chain = "pattern 64 32 mode=grid from=ff8800 to=#102030 size=8 line=2";
+
+    // when
+    let commands = split_command_vector(&words(chain)).unwrap();
+    let script = to_script(&commands);
+
+    // then
+    let expected = PatternParams {
+        width: 64,
+        height: 32,
+        mode: Mode::Grid,
+        from: [255, 136, 0],
+        to: [16, 32, 48],
+        size: 8,
+        line: 2,
+        ..PatternParams::default()
+    };
+    assert_eq!(commands, vec![ChainCommands::Pattern { params: expected }]);
+    assert_eq!(script, "pattern 64 32 grid #ff8800 #102030 8 0 2\n");
+    assert_eq!(split_command_vector(&words(&script)).unwrap(), commands);
+    for chain in ["pattern 0", "pattern mode=waves", "pattern from=red", "pattern size=0", "pattern size=4 line=5"] {
+        let err = split_command_vector(&words(chain)).unwrap_err();
+        assert!(matches!(err, MirageError::InvalidArgument { .. }), "{}", chain);
+    }
+}
+
+#[test]
+fn pattern_gradients_run_between_the_colors_test() {
+    // given / when
+    let linear = generate("pattern 100 10 mode=linear from=#000000 to=#ffffff");
+    let vertical = generate("pattern 10 100 mode=linear angle=90 from=#000000 to=#ffffff");
+    let radial = generate("pattern 101 101 mode=radial from=#ffffff to=#000000");
+
+    // then
+    assert!(linear.get_pixel(0, 5).0[0] < 3 && linear.get_pixel(99, 5).0[0] > 252);
+    assert_eq!(linear.get_pixel(50, 0), linear.get_pixel(50, 9));
+    // Counterclockwise from pointing right is pointing up.
+    assert!(vertical.get_pixel(5, 99).0[0] < 3 && vertical.get_pixel(5, 0).0[0] > 252);
+    assert_eq!(radial.get_pixel(50, 50).0[0], 255);
+    assert!(radial.get_pixel(0, 0).0[0] < 5);
+    assert_eq!(radial.get_pixel(0, 50), radial.get_pixel(50, 0));
+}
+
+#[test]
+fn pattern_tiles_checkerboard_stripes_and_grid_test() {
+    // given / when
+    let checkerboard = generate("pattern 40 40 mode=checkerboard size=10");
+    let stripes = generate("pattern 40 40 mode=stripes size=10 angle=90");
+    let grid = generate("pattern 40 40 mode=grid size=10 line=2");
+    let solid = generate("pattern 3 2 from=#123456");
+
+    // then
+    let white = [255, 255, 255, 255];
+    let black = [0, 0, 0, 255];
+    assert_eq!(checkerboard.get_pixel(0, 0).0, white);
+    assert_eq!(checkerboard.get_pixel(10, 0).0, black);
+    assert_eq!(checkerboard.get_pixel(10, 10).0, white);
+    // At 90 degrees the stripes run up and down.
+    assert_eq!(stripes.get_pixel(5, 0).0, white);
+    assert_eq!(stripes.get_pixel(5, 39).0, white);
+    assert_eq!(stripes.get_pixel(15, 20).0, black);
+    assert_eq!(grid.get_pixel(21, 5).0, white);
+    assert_eq!(grid.get_pixel(5, 11).0, white);
+    assert_eq!(grid.get_pixel(5, 5).0, black);
+    assert_eq!(solid.dimensions(), (3, 2));
+    assert!(solid.to_rgb8().pixels().all(|pixel| pixel.0 == [0x12, 0x34, 0x56]));
+}
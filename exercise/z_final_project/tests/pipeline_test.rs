@@ -0,0 +1,88 @@
+use image::{DynamicImage, GenericImageView, RgbImage};
+use mirage::blur::Kernel;
+use mirage::crop::CropParams;
+use mirage::pipeline::{execute, execute_with, validate};
+use mirage::pattern::PatternParams;
+use mirage::rotate::RotateParams;
+use mirage::{ChainCommands, MirageError};
+
+fn pattern() -> ChainCommands {
+    ChainCommands::Pattern { params: PatternParams { width: 100, height: 100, ..PatternParams::default() } }
+}
+
+#[test]
+fn execute_threads_image_through_every_step_test() {
+    // given
+    let image = DynamicImage::ImageRgb8(RgbImage::new(40, 20));
+    let commands = vec![
+        ChainCommands::Crop { params: CropParams::pixels(0, 0, 30, 10) },
+        ChainCommands::Rotate { params: RotateParams::default() },
+        ChainCommands::Invert {},
+    ];
+
+    // when
+    let result = execute(Some(image), &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(result.dimensions(), (10, 30));
+    assert_eq!(result.get_pixel(0, 0).0, [255, 255, 255, 255]);
+}
+
+#[test]
+fn execute_without_image_starts_from_generator_test() {
+    // given
+    let commands = vec![pattern(), ChainCommands::Crop { params: CropParams::pixels(0, 0, 50, 25) }];
+
+    // when
+    let result = execute(None, &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(result.dimensions(), (50, 25));
+}
+
+#[test]
+fn generators_must_start_a_chain_without_input_test() {
+    // given
+    let image = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+    let after_transform = vec![ChainCommands::Blur { sigma: 2.0, kernel: Kernel::Gaussian }, pattern()];
+    let with_input = vec![pattern()];
+
+    // when
+    let misplaced = execute(None, &after_transform).unwrap_err();
+    let over_input = execute(Some(image), &with_input).unwrap_err();
+
+    // then
+    assert!(matches!(&misplaced, MirageError::MisplacedGenerator { command, position: 2 } if command == "pattern"));
+    assert!(matches!(over_input, MirageError::MisplacedGenerator { position: 1, .. }));
+    assert_eq!(misplaced.exit_code(), 14);
+    assert!(misplaced.is_usage_error());
+    assert!(validate(false, &with_input).is_ok());
+    assert!(validate(true, &[ChainCommands::Invert {}]).is_ok());
+}
+
+#[test]
+fn execute_with_shows_each_step_its_input_test() {
+    // given
+    let commands = vec![pattern(), ChainCommands::Crop { params: CropParams::pixels(0, 0, 30, 10) }, ChainCommands::Invert {}];
+    let mut seen = Vec::new();
+
+    // when
+    let result = execute_with(None, &commands, |command, image| seen.push((command.clone(), image.map(|img| img.dimensions()))));
+
+    // then
+    assert_eq!(result.unwrap().unwrap().dimensions(), (30, 10));
+    assert_eq!(seen, vec![(commands[0].clone(), None), (commands[1].clone(), Some((100, 100))), (commands[2].clone(), Some((30, 10)))]);
+}
+
+#[test]
+fn generators_larger_than_the_pixel_budget_are_rejected_test() {
+    // given
+    let huge = ChainCommands::Pattern { params: PatternParams { width: 100_000, height: 100_000, ..PatternParams::default() } };
+
+    // when
+    let err = validate(false, &[huge]).unwrap_err();
+
+    // then
+    assert!(matches!(&err, MirageError::InvalidArgument { command, value, .. } if command == "pattern" && value == "100000x100000"));
+    assert!(validate(false, &[pattern()]).is_ok());
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use mirage::pipeline::execute;
+use mirage::resize::{resize, thumbnail, Filter, Mode, ResizeParams, Size};
+use mirage::script::to_script;
+use mirage::{split_command_vector, MirageError};
+use common::words;
+
+fn wide() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(400, 100, |x, y| Rgb([(x / 2) as u8, (y * 2) as u8, 50])))
+}
+
+#[test]
+fn sizes_keep_the_aspect_ratio_test() {
+    // given
+    let sizes = ["200", "200x", "x50", "50%", "30x40", "0.5%"];
+
+    // when
+    let dimensions: Vec<(u32, u32)> = sizes.iter().map(|size| size.parse::<Size>().unwrap().dimensions(400, 100).unwrap()).collect();
+
+    // then
+    assert_eq!(dimensions, [(200, 50), (200, 50), (200, 50), (200, 50), (30, 40), (2, 1)]);
+    for bad in ["0x10", "10x0", "x", "abc", "-5%", "0%", "10x20x30"] {
+        assert!(bad.parse::<Size>().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn modes_match_a_box_test() {
+    // given
+    let image = wide();
+    let into_box = |mode| ResizeParams { size: Size::Box(100, 100), mode, filter: Filter::Triangle };
+
+    // when
+    let exact = resize(&image, &into_box(Mode::Exact)).unwrap();
+    let fit = resize(&image, &into_box(Mode::Fit)).unwrap();
+    let fill = resize(&image, &into_box(Mode::Fill)).unwrap();
+    let cover = resize(&image, &into_box(Mode::Cover)).unwrap();
+
+    // then
+    assert_eq!(exact.dimensions(), (100, 100));
+    assert_eq!(fit.dimensions(), (100, 25));
+    assert_eq!(fill.dimensions(), (100, 100));
+    assert_eq!(cover.dimensions(), (400, 100));
+    // Fill keeps the middle of the wide image.
+    assert!((fill.get_pixel(0, 50).0[0] as i32 - 75).abs() <= 2, "{:?}", fill.get_pixel(0, 50));
+}
+
+#[test]
+fn every_filter_scales_test() {
+    // given
+    let small = DynamicImage::ImageRgb8(RgbImage::from_fn(3, 2, |x, y| Rgb([x as u8 * 80, y as u8 * 80, 0])));
+
+    for filter in [Filter::Nearest, Filter::Triangle, Filter::CatmullRom, Filter::Gaussian, Filter::Lanczos3] {
+        // when
+        let result = resize(&small, &ResizeParams { size: Size::Percent(200.0), filter, ..ResizeParams::default() }).unwrap();
+
+        // then
+        assert_eq!(result.dimensions(), (6, 4), "{}", filter);
+        if filter == Filter::Nearest {
+            assert_eq!(result.get_pixel(5, 3), small.get_pixel(2, 1));
+            assert_eq!(result.get_pixel(2, 1), small.get_pixel(1, 0));
+        }
+    }
+}
+
+#[test]
+fn resize_and_thumbnail_commands_test() {
+    // given
+    let chain = "thumbnail 100x100 / resize 50% / resize x20 cover catmull-rom";
+
+    // when
+    let commands = split_command_vector(&words(chain)).unwrap();
+    let result = execute(Some(wide()), &commands).unwrap().unwrap();
+    let errors: Vec<MirageError> = ["resize 0x5", "resize 10 mode=stretch", "resize 10 filter=bicubic", "thumbnail big"]
+        .iter()
+        .map(|chain| split_command_vector(&words(chain)).unwrap_err())
+        .collect();
+
+    // then
+    // 400x100 fits in 100x100 as 100x25, then 50x13 (12.5 rounded up), then
+    // 77x20.
+    assert_eq!(result.dimensions(), (77, 20));
+    assert_eq!(to_script(&commands), "thumbnail 100x100\nresize 50% exact lanczos3\nresize x20 cover catmull-rom\n");
+    assert!(errors.iter().all(|error| matches!(error, MirageError::InvalidArgument { .. })), "{:?}", errors);
+}
+
+#[test]
+fn sizes_beyond_the_pixel_budget_are_rejected_test() {
+    // given
+    let image = wide();
+    let chains = ["resize 1000000%", "resize 100000x100000", "resize 100000x10 cover", "thumbnail 5000000000%"];
+
+    for chain in chains {
+        // when
+        let commands = split_command_vector(&words(chain)).unwrap();
+        let err = execute(Some(image.clone()), &commands).unwrap_err();
+
+        // then
+        assert!(matches!(&err, MirageError::InvalidArgument { reason, .. } if reason.contains("pixels")), "{}: {:?}", chain, err);
+    }
+    assert_eq!(Size::Percent(1e12).dimensions(400, 100), None);
+    assert_eq!(thumbnail(&image, Size::Box(40, 40)).unwrap().dimensions(), (40, 10));
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use image::{ColorType, DynamicImage, GenericImageView, GrayImage, Luma, Rgb, RgbImage};
+use mirage::rotate::{rotate, Canvas, Fill, Interpolation, RotateParams};
+use mirage::script::to_script;
+use mirage::{split_command_vector, MirageError, Rotation};
+use common::words;
+
+fn numbered(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| Rgb([x as u8 * 10, y as u8 * 10, 99])))
+}
+
+#[test]
+fn quarter_turns_move_pixels_exactly_test() {
+    // given
+    let image = numbered(5, 3);
+    let by = |angle, canvas| RotateParams { angle, canvas, interpolation: Interpolation::Bicubic, ..RotateParams::default() };
+
+    // when
+    let ninety = rotate(&image, &by(90.0, Canvas::Expand));
+    let minus_ninety = rotate(&image, &by(-90.0, Canvas::Expand));
+    let full_turns = rotate(&image, &by(720.0, Canvas::Crop));
+    let half_turn = rotate(&image, &by(540.0, Canvas::Crop));
+    let cropped = rotate(&numbered(4, 2), &RotateParams { angle: 90.0, canvas: Canvas::Crop, ..RotateParams::default() });
+
+    // then
+    assert_eq!(ninety, image.rotate90());
+    assert_eq!(minus_ninety, image.rotate270());
+    assert_eq!(full_turns, image);
+    assert_eq!(half_turn, image.rotate180());
+    // Turning a 4x2 image in place, the second column of the output shows the
+    // middle of the bottom row of the source; the sides are filled.
+    assert_eq!(cropped.dimensions(), (4, 2));
+    assert_eq!(cropped.get_pixel(1, 0).0, [10, 10, 99, 255]);
+    assert_eq!(cropped.get_pixel(1, 1).0, [20, 10, 99, 255]);
+    assert_eq!(cropped.get_pixel(2, 0).0, [10, 0, 99, 255]);
+    assert_eq!(cropped.get_pixel(0, 0).0, [0, 0, 0, 255]);
+}
+
+#[test]
+fn quarter_turn_angles_go_through_rotation_test() {
+    // given
+    let image = numbered(5, 3);
+    let cases = [(90.0, Some(Rotation::Ninety)), (-180.0, Some(Rotation::OneEighty)), (630.0, Some(Rotation::TwoSeventy)),
+                 (45.0, None), (0.0, None)];
+
+    for (angle, expected) in cases {
+        let params = RotateParams { angle, ..RotateParams::default() };
+
+        // when
+        let result = params.quarter_turn();
+
+        // then
+        assert_eq!(result, expected, "{}", angle);
+        if let Some(rotation) = result {
+            assert_eq!(rotate(&image, &params), rotation.apply(&image), "{}", angle);
+        }
+    }
+    assert_eq!("270".parse::<Rotation>().unwrap().to_string(), "270");
+    assert!(matches!("45".parse::<Rotation>(), Err(MirageError::InvalidRotation(_))));
+}
+
+#[test]
+fn expanded_canvas_fits_the_rotated_image_test() {
+    // given
+    let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(100, 50, Rgb([200, 150, 100])));
+    let params = RotateParams { angle: 45.0, fill: Fill::Color([0, 0, 255]), ..RotateParams::default() };
+
+    // when
+    let result = rotate(&image, &params);
+    let transparent = rotate(&image, &RotateParams { fill: Fill::Transparent, ..params });
+
+    // then
+    // 150 / sqrt(2) = 106.07 on both sides.
+    assert_eq!(result.dimensions(), (107, 107));
+    assert_eq!(result.color(), ColorType::Rgb8);
+    assert_eq!(result.get_pixel(53, 53).0, [200, 150, 100, 255]);
+    assert_eq!(result.get_pixel(0, 0).0, [0, 0, 255, 255]);
+    assert_eq!(transparent.color(), ColorType::Rgba8);
+    assert_eq!(transparent.get_pixel(0, 0).0[3], 0);
+    // Premultiplied interpolation keeps the color of edge pixels that are
+    // partly transparent.
+    let edge = (0..107).map(|x| transparent.get_pixel(x, 53).0).find(|pixel| pixel[3] > 0).unwrap();
+    assert!(edge[3] < 255);
+    assert_eq!(&edge[..3], &[200, 150, 100]);
+}
+
+#[test]
+fn cropped_quarter_turns_of_odd_shapes_stay_exact_test() {
+    // given
+    let image = numbered(5, 2);
+
+    // when
+    let result = rotate(&image, &RotateParams { angle: 90.0, canvas: Canvas::Crop, ..RotateParams::default() });
+
+    // then
+    // The 2x5 turned image sits one column in from the left, with two of its
+    // rows cut off at the top and one at the bottom.
+    let turned = image.rotate90();
+    assert_eq!(result.dimensions(), (5, 2));
+    assert_eq!(result.get_pixel(1, 0), turned.get_pixel(0, 2));
+    assert_eq!(result.get_pixel(2, 1), turned.get_pixel(1, 3));
+    assert_eq!(result.get_pixel(3, 0).0, [0, 0, 0, 255]);
+    assert_eq!(result.get_pixel(0, 1).0, [0, 0, 0, 255]);
+}
+
+#[test]
+fn grayscale_stays_grayscale_test() {
+    // given
+    let image = DynamicImage::ImageLuma8(GrayImage::from_fn(30, 20, |x, y| Luma([(x * 8 + y) as u8])));
+
+    // when
+    let turned = rotate(&image, &RotateParams { angle: 30.0, ..RotateParams::default() });
+    let transparent = rotate(&image, &RotateParams { angle: 30.0, fill: Fill::Transparent, ..RotateParams::default() });
+    let cropped = rotate(&image, &RotateParams { angle: 270.0, canvas: Canvas::Crop, ..RotateParams::default() });
+
+    // then
+    assert_eq!(turned.color(), ColorType::L8);
+    assert_eq!(transparent.color(), ColorType::La8);
+    assert_eq!(cropped.color(), ColorType::L8);
+}
+
+#[test]
+fn interpolations_keep_flat_areas_flat_test() {
+    // given
+    let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(40, 40, Rgb([30, 60, 90])));
+
+    for interpolation in [Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Bicubic] {
+        // when
+        let params = RotateParams { angle: 33.0, interpolation, canvas: Canvas::Crop, fill: Fill::Color([255, 255, 255]) };
+        let result = rotate(&image, &params);
+
+        // then
+        assert_eq!(result.dimensions(), (40, 40), "{}", interpolation);
+        assert_eq!(result.get_pixel(20, 20).0, [30, 60, 90, 255], "{}", interpolation);
+        assert_eq!(result.get_pixel(0, 0).0, [255, 255, 255, 255], "{}", interpolation);
+    }
+}
+
+#[test]
+fn rotate_parses_angle_and_options_test() {
+    // given
+    let chain = "rotate 30 / rotate -12.5 bicubic crop transparent / rotate 90 fill=#102030 interpolation=nearest";
+
+    // when
+    let commands = split_command_vector(&words(chain)).unwrap();
+    let bad_angle = split_command_vector(&words("rotate NaN")).unwrap_err();
+    let errors: Vec<MirageError> = ["rotate 10 cubic", "rotate 10 canvas=grow", "rotate 10 fill=red"]
+        .iter()
+        .map(|chain| split_command_vector(&words(chain)).unwrap_err())
+        .collect();
+
+    // then
+    assert_eq!(
+        to_script(&commands),
+        "rotate 30 bilinear expand #000000\n\
+         rotate -12.5 bicubic crop transparent\n\
+         rotate 90 nearest expand #102030\n"
+    );
+    assert!(matches!(bad_angle, MirageError::InvalidRotation(angle) if angle == "NaN"));
+    assert!(errors.iter().all(|error| matches!(error, MirageError::InvalidArgument { .. })), "{:?}", errors);
+}
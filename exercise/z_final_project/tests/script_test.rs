@@ -0,0 +1,90 @@
+use std::fs;
+use mirage::crop::CropParams;
+use mirage::pattern::{Mode, PatternParams};
+use mirage::script::{parse_script, to_script};
+use mirage::rotate::RotateParams;
+use mirage::{split_command_vector, ChainCommands, MirageError, Registry};
+
+#[test]
+fn parse_script_matches_command_line_test() {
+    // given
+    let script = "# straighten the scan\n\
+                  blur\n\
+                  \n\
+                  rotate 90\n   \
+                  # then cut it down\n\
+                  crop 0 0 200 200\n\
+                  brighten 10\n";
+    let commands: Vec<String> = "blur / rotate 90 / crop 0 0 200 200 / brighten 10"
+        .split_whitespace().map(String::from).collect();
+
+    // when
+    let result = parse_script(script, &Registry::default()).unwrap();
+
+    // then
+    assert_eq!(result, split_command_vector(&commands).unwrap());
+}
+
+#[test]
+fn parse_script_reports_line_number_test() {
+    // given
+    let script = "blur\n\n# comment\nrotate left\n";
+
+    // when
+    let result = parse_script(script, &Registry::default());
+
+    // then
+    let err = result.unwrap_err();
+    assert!(matches!(&err, MirageError::Script { line: 4, source } if matches!(**source, MirageError::InvalidRotation(_))));
+    assert_eq!(err.to_string(), "script line 4: invalid rotation 'left', must be an angle in degrees");
+}
+
+#[test]
+fn to_script_round_trips_test() {
+    // given
+    let commands = vec![
+        ChainCommands::Pattern { params: PatternParams { mode: Mode::Stripes, from: [1, 2, 3], ..PatternParams::default() } },
+        ChainCommands::Rotate { params: RotateParams { angle: 270.0, ..RotateParams::default() } },
+        ChainCommands::Crop { params: CropParams::pixels(1, 2, 30, 40) },
+        ChainCommands::Brighten { brightness: -5 },
+        ChainCommands::Grayscale {},
+    ];
+
+    // when
+    let script = to_script(&commands);
+
+    // then
+    assert_eq!(script, "pattern 256 256 stripes #010203 #000000 32 0 1\nrotate 270 bilinear expand #000000\ncrop 1 2 30 40 none\nbrighten -5\ngrayscale\n");
+    assert_eq!(parse_script(&script, &Registry::default()).unwrap(), commands);
+}
+
+#[test]
+fn values_with_spaces_and_quotes_round_trip_test() {
+    // given
+    let path = std::env::temp_dir().join(format!("mirage \"maps\" \\ {}.txt", std::process::id()));
+    fs::write(&path, "0.5 0 0 0.5 0 0\n0.5 0 0 0.5 0.5 0\n").unwrap();
+    let commands = split_command_vector(&["ifs".to_string(), format!("system={}", path.display())]).unwrap();
+
+    // when
+    let script = to_script(&commands);
+    let result = parse_script(&script, &Registry::default());
+
+    // then
+    fs::remove_file(&path).unwrap();
+    assert!(script.contains(" \"/"), "{}", script);
+    assert_eq!(result.unwrap(), commands);
+}
+
+#[test]
+fn unclosed_quotes_are_reported_with_their_line_test() {
+    // given
+    let script = "blur\nifs system=\"my maps.txt\n";
+
+    // when
+    let result = parse_script(script, &Registry::default());
+
+    // then
+    let err = result.unwrap_err();
+    assert!(matches!(&err, MirageError::Script { line: 2, source } if matches!(**source, MirageError::InvalidArgument { .. })));
+    assert_eq!(err.exit_code(), 10);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage, Rgba, RgbaImage};
+use mirage::crop::CropParams;
+use mirage::pipeline::execute;
+use mirage::script::to_script;
+use mirage::trim::{rectangle, Border, TrimParams};
+use mirage::{split_command_vector, ChainCommands};
+use common::{rejection, words};
+
+/// A gray block at (5, 2) to (8, 5) on white, with a speck of near-white.
+fn scan() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(20, 10, |x, y| match (x, y) {
+        (5..=8, 2..=5) => Rgb([90, 90, 90]),
+        (1, 8) => Rgb([250, 250, 245]),
+        _ => Rgb([255, 255, 255]),
+    }))
+}
+
+#[test]
+fn trim_finds_content_within_tolerance_test() {
+    // given
+    let image = scan();
+
+    // when
+    let exact = rectangle(&image, &TrimParams::default());
+    let tolerant = rectangle(&image, &TrimParams { tolerance: 10, ..TrimParams::default() });
+
+    // then
+    assert_eq!(exact, Some(CropParams::pixels(1, 2, 8, 7)));
+    assert_eq!(tolerant, Some(CropParams::pixels(5, 2, 4, 4)));
+}
+
+#[test]
+fn trim_removes_transparent_margins_test() {
+    // given
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(12, 12, |x, y| {
+        if (3..9).contains(&x) && (4..6).contains(&y) {
+            Rgba([255, 0, 0, 255])
+        } else {
+            Rgba([255, 255, 255, 0])
+        }
+    }));
+    let commands = split_command_vector(&words("trim border=transparent")).unwrap();
+
+    // when
+    let result = execute(Some(image.clone()), &commands).unwrap().unwrap();
+    let empty = DynamicImage::ImageRgba8(RgbaImage::new(5, 5));
+    let untouched = execute(Some(empty.clone()), &commands).unwrap().unwrap();
+
+    // then
+    assert_eq!(result.dimensions(), (6, 2));
+    assert!(result.pixels().all(|(_, _, pixel)| pixel == Rgba([255, 0, 0, 255])));
+    assert_eq!(rectangle(&empty, &TrimParams { border: Border::Transparent, ..TrimParams::default() }), None);
+    assert_eq!(untouched, empty);
+}
+
+#[test]
+fn trimmed_rectangle_is_reusable_as_crop_test() {
+    // given
+    let image = scan();
+    let params = TrimParams { tolerance: 10, border: Border::Corner };
+
+    // when
+    let crop = ChainCommands::Crop { params: rectangle(&image, &params).unwrap() };
+    let script = to_script(&[crop]);
+    let trimmed = execute(Some(image.clone()), &[ChainCommands::Trim { params }]).unwrap().unwrap();
+    let cropped = execute(Some(image), &split_command_vector(&words(&script)).unwrap()).unwrap().unwrap();
+
+    // then
+    assert_eq!(script, "crop 5 2 4 4 none\n");
+    assert_eq!(trimmed, cropped);
+}
+
+#[test]
+fn trim_tolerance_is_one_channel_step_test() {
+    // given
+    let commands = split_command_vector(&words("trim 255 transparent / trim")).unwrap();
+
+    // when
+    let script = to_script(&commands);
+
+    // then
+    assert_eq!(commands[0], ChainCommands::Trim { params: TrimParams { tolerance: 255, border: Border::Transparent } });
+    assert_eq!(script, "trim 255 transparent\ntrim 0 corner\n");
+    for (chain, value) in [("trim 256", "256"), ("trim -1", "-1"), ("trim 0.5", "0.5")] {
+        assert_eq!(rejection(chain), (value.to_string(), "must be between 0 and 255".to_string()));
+    }
+    assert_eq!(rejection("trim border=white").0, "white");
+}